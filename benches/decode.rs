@@ -0,0 +1,44 @@
+//! Benchmarks decoding the Accept-Encoding values browsers actually send
+//!
+//! A std-only stand-in for a criterion benchmark, as criterion is not a dependency:
+//! `Instant` measures the time per decode. The allocation counts are verified by
+//! `tests/allocations.rs`.
+//!
+//! Run with `cargo bench --bench decode`.
+
+use http_encoding_headers::{decode_header_value, decode_header_value_into};
+use std::hint::black_box;
+use std::time::Instant;
+
+const BROWSER_VALUES: [&str; 4] = [
+    "gzip, deflate",
+    "gzip, deflate, br",
+    "gzip, deflate, br, zstd",
+    "gzip, deflate, br, zstd, identity;q=0.5, *;q=0",
+];
+
+const ITERATIONS: u32 = 1_000_000;
+
+fn main() {
+    for value in BROWSER_VALUES {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            black_box(decode_header_value(black_box(value)).unwrap());
+        }
+        let per_decode = start.elapsed() / ITERATIONS;
+
+        println!("{value:<48} {per_decode:>10.2?}/decode");
+    }
+
+    let mut buffer = Vec::new();
+    for value in BROWSER_VALUES {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            decode_header_value_into(black_box(value), &mut buffer).unwrap();
+            black_box(&buffer);
+        }
+        let per_decode = start.elapsed() / ITERATIONS;
+
+        println!("{value:<48} {per_decode:>10.2?}/decode into buffer");
+    }
+}
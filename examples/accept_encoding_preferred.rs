@@ -155,7 +155,7 @@ fn main() {
     }
 
     // Test server filtering with equal qualities
-    let partial_server = vec![Encoding::Deflate, Encoding::Br];
+    let partial_server = [Encoding::Deflate, Encoding::Br];
     if let Some(preferred_filtered) = equal_accept.preferred_allowed(partial_server.iter()) {
         println!(
             "   Preferred (server filtered from equal qualities): {} (first allowed match)",
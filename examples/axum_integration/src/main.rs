@@ -5,10 +5,13 @@
 //! - Content negotiation based on client preferences
 //! - Responding with appropriate Content-Encoding headers
 //! - Error handling for missing or invalid Accept-Encoding headers
+//! - A native extractor built on `AcceptEncoding::from_headers`, without `axum_extra`
 
-use axum::{extract::{Query, State}, http, http::StatusCode, response::{IntoResponse, Response}, routing::get, Json, Router};
+use axum::{extract::{FromRequestParts, OptionalFromRequestParts, Query, State}, http, http::request::Parts, http::StatusCode, response::{IntoResponse, Response}, routing::get, Json, Router};
 use axum_extra::extract::TypedHeader;
-use http_encoding_headers::{AcceptEncoding, ContentEncoding, Encoding};
+use http_encoding_headers::{
+    AcceptEncoding, AcceptEncodingRejection, ContentEncoding, Encoding, NegotiationResult,
+};
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
 
@@ -39,6 +42,43 @@ fn default_server_encodings() -> String {
     "gzip,deflate,br".to_string()
 }
 
+/// Extracts `AcceptEncoding` directly from the request headers.
+///
+/// A missing header is rejected, use `Option<ClientAcceptEncoding>` if the header is optional.
+/// The crate has no `axum` feature yet, so the extractor impls live in this newtype.
+struct ClientAcceptEncoding(AcceptEncoding);
+
+/// Rejection responding with `400 Bad Request` for missing or malformed headers
+struct ClientAcceptEncodingRejection(AcceptEncodingRejection);
+
+impl IntoResponse for ClientAcceptEncodingRejection {
+    fn into_response(self) -> Response {
+        (self.0.status(), self.0.to_string()).into_response()
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientAcceptEncoding {
+    type Rejection = ClientAcceptEncodingRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        AcceptEncoding::from_headers(&parts.headers)
+            .map(ClientAcceptEncoding)
+            .map_err(ClientAcceptEncodingRejection)
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for ClientAcceptEncoding {
+    type Rejection = ClientAcceptEncodingRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Option<Self>, Self::Rejection> {
+        match AcceptEncoding::from_headers(&parts.headers) {
+            Ok(accept_encoding) => Ok(Some(ClientAcceptEncoding(accept_encoding))),
+            Err(AcceptEncodingRejection::Missing) => Ok(None),
+            Err(rejection) => Err(ClientAcceptEncodingRejection(rejection)),
+        }
+    }
+}
+
 /// Application state containing server configuration
 #[derive(Clone)]
 struct AppState {
@@ -63,6 +103,7 @@ async fn main() {
         .route("/", get(root_handler))
         .route("/api/data", get(api_handler))
         .route("/negotiate", get(negotiate_handler))
+        .route("/native", get(native_handler))
         .with_state(state);
 
     println!("Server running on http://localhost:3000");
@@ -70,6 +111,7 @@ async fn main() {
     println!("  GET /                 - Simple AcceptEncoding extraction example");
     println!("  GET /api/data         - Content negotiation with JSON response");
     println!("  GET /negotiate        - Advanced negotiation with server config");
+    println!("  GET /native           - Native extractor, 400 on missing or malformed header");
     println!("\nExample requests:");
     println!("  curl -H 'Accept-Encoding: gzip, deflate;q=0.8, br;q=0.9' http://localhost:3000/");
     println!("  curl -H 'Accept-Encoding: br;q=1.0, gzip;q=0.5' http://localhost:3000/api/data");
@@ -121,6 +163,14 @@ async fn root_handler(
     }
 }
 
+/// Handler using the native extractor; responds with 400 if the header is missing or malformed
+async fn native_handler(ClientAcceptEncoding(accept_encoding): ClientAcceptEncoding) -> String {
+    match accept_encoding.preferred() {
+        Some(preferred) => format!("Preferred encoding: {}", preferred),
+        None => "No preferred encoding".to_string(),
+    }
+}
+
 /// API handler with content negotiation and JSON response
 async fn api_handler(
     State(state): State<AppState>,
@@ -129,7 +179,7 @@ async fn api_handler(
     let response_data = match accept_encoding.as_ref() {
         Some(TypedHeader(accept_encoding)) => {
             // Perform content negotiation
-            let selected_encoding = negotiate_encoding(accept_encoding, &state.supported_encodings);
+            let selected_encoding = accept_encoding.best_or_identity(&state.supported_encodings);
 
             ApiResponse {
                 message: "Content negotiation successful!".to_string(),
@@ -160,12 +210,12 @@ async fn api_handler(
 
     // Create response with appropriate Content-Encoding header
     let selected_encoding = response_data.encoding_used.parse::<Encoding>().unwrap_or(Encoding::Identity);
-    let content_encoding = ContentEncoding::new(selected_encoding);
-
     let mut response = Json(response_data).into_response();
 
-    // Add Content-Encoding header using TypedHeader
-    if let Ok(header_value) = http::HeaderValue::from_str(&content_encoding.encoding().to_string()) {
+    // Add Content-Encoding header, negotiation never selects the rejected wildcard
+    if let Ok(content_encoding) = ContentEncoding::new(selected_encoding)
+        && let Ok(header_value) = http::HeaderValue::from_str(&content_encoding.to_string())
+    {
         response.headers_mut().insert(
             http::header::CONTENT_ENCODING,
             header_value,
@@ -196,26 +246,16 @@ async fn negotiate_handler(
 
     match accept_encoding {
         Some(TypedHeader(accept_encoding)) => {
-            let selected = negotiate_encoding(&accept_encoding, &server_encodings);
-
-            // Create detailed negotiation information
-            let mut sorted_accept = accept_encoding.clone();
-            sorted_accept.sort_descending();
+            // `explain` reports every client entry and the outcome of `negotiate`
+            let selected = match accept_encoding.negotiate(&server_encodings) {
+                NegotiationResult::Selected(encoding) => encoding.to_string(),
+                NegotiationResult::Identity => "identity".to_string(),
+                NegotiationResult::NotAcceptable => return Err(StatusCode::NOT_ACCEPTABLE),
+            };
 
             negotiation_result["negotiation"] = serde_json::json!({
-                "selected_encoding": selected.to_string(),
-                "client_preferences": sorted_accept.items()
-                    .iter()
-                    .map(|(enc, q)| serde_json::json!({
-                        "encoding": enc.to_string(),
-                        "quality": q,
-                        "supported_by_server": server_encodings.contains(enc)
-                    }))
-                    .collect::<Vec<_>>(),
-                "negotiation_process": {
-                    "method": "Iterate client preferences in quality order",
-                    "result": format!("Selected '{}' (first acceptable match)", selected)
-                }
+                "selected_encoding": selected,
+                "explanation": accept_encoding.explain(&server_encodings)
             });
         }
         None => {
@@ -228,20 +268,3 @@ async fn negotiate_handler(
 
     Ok(Json(negotiation_result))
 }
-
-/// Content negotiation algorithm
-fn negotiate_encoding(accept_encoding: &AcceptEncoding, server_supported: &[Encoding]) -> Encoding {
-    // Create a copy and sort by preference (highest quality first)
-    let mut sorted_accept = accept_encoding.clone();
-    sorted_accept.sort_descending();
-
-    // Find first acceptable encoding
-    for (encoding, quality) in sorted_accept.items() {
-        if *quality > 0.0 && server_supported.contains(encoding) {
-            return encoding.clone();
-        }
-    }
-
-    // Fallback to identity if no match found
-    Encoding::Identity
-}
\ No newline at end of file
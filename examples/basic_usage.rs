@@ -5,13 +5,16 @@
 //! - Encoding and decoding Content-Encoding headers
 //! - Using AcceptEncoding methods like preferred() and sorting
 
+use http_encoding_headers::negotiation::{NegotiationResult, negotiate};
 use http_encoding_headers::{
-    AcceptEncoding, ContentEncoding, Encoding, decode_header_value, encode_header_value,
+    AcceptEncoding, Encoding, decode_header_value, encode_header_value, supported_encodings,
 };
 
 #[cfg(feature = "http_crates")]
 use headers::{Header, HeaderMapExt};
 #[cfg(feature = "http_crates")]
+use http_encoding_headers::ContentEncoding;
+#[cfg(feature = "http_crates")]
 use http::{HeaderMap, HeaderValue};
 
 fn main() {
@@ -93,7 +96,7 @@ fn content_encoding_examples() {
 
     // Example 2a: Creating and encoding Content-Encoding headers
     println!("\n2a. Creating and encoding Content-Encoding:");
-    let content_encoding = ContentEncoding::new(Encoding::Gzip);
+    let content_encoding = ContentEncoding::new(Encoding::Gzip).unwrap();
     println!("   Created ContentEncoding: {:?}", content_encoding);
 
     // Use with HeaderMap
@@ -113,7 +116,7 @@ fn content_encoding_examples() {
 
     for encoding_str in test_values {
         println!("   Testing: {}", encoding_str);
-        let header_values = vec![HeaderValue::from_str(encoding_str).unwrap()];
+        let header_values = [HeaderValue::from_str(encoding_str).unwrap()];
 
         match ContentEncoding::decode(&mut header_values.iter()) {
             Ok(decoded) => println!("     Decoded: {:?}", decoded),
@@ -121,28 +124,25 @@ fn content_encoding_examples() {
         }
     }
 
-    // Example 2c: Multiple identical values (valid)
-    println!("\n2c. Multiple identical Content-Encoding values:");
-    let identical_values = vec![
-        HeaderValue::from_str("gzip").unwrap(),
+    // Example 2c: Multiple header lines stack in order
+    println!("\n2c. Multiple Content-Encoding header lines:");
+    let stacked_values = [
         HeaderValue::from_str("gzip").unwrap(),
+        HeaderValue::from_str("br").unwrap(),
     ];
 
-    match ContentEncoding::decode(&mut identical_values.iter()) {
-        Ok(decoded) => println!("   Multiple identical values decoded: {:?}", decoded),
+    match ContentEncoding::decode(&mut stacked_values.iter()) {
+        Ok(decoded) => println!("   Stacked encodings (applied in order): {}", decoded),
         Err(e) => println!("   Error: {:?}", e),
     }
 
-    // Example 2d: Conflicting values (should error)
-    println!("\n2d. Conflicting Content-Encoding values (should error):");
-    let conflicting_values = vec![
-        HeaderValue::from_str("gzip").unwrap(),
-        HeaderValue::from_str("deflate").unwrap(),
-    ];
+    // Example 2d: A single comma-separated value decodes the same way
+    println!("\n2d. Comma-separated Content-Encoding value:");
+    let joined_values = [HeaderValue::from_str("gzip, br").unwrap()];
 
-    match ContentEncoding::decode(&mut conflicting_values.iter()) {
-        Ok(decoded) => println!("   Unexpectedly decoded: {:?}", decoded),
-        Err(_) => println!("   Correctly rejected conflicting values"),
+    match ContentEncoding::decode(&mut joined_values.iter()) {
+        Ok(decoded) => println!("   Stacked encodings (applied in order): {}", decoded),
+        Err(e) => println!("   Error: {:?}", e),
     }
 }
 
@@ -236,31 +236,21 @@ fn practical_content_negotiation_example() {
     println!("   Client Accept-Encoding: {}", client_header);
 
     // Server supported encodings (in order of preference)
-    let server_supported = vec![Encoding::Gzip, Encoding::Deflate, Encoding::Identity];
+    let server_supported = supported_encodings!(gzip, deflate, identity);
     println!("   Server supported: {:?}", server_supported);
 
     // Parse client preferences
-    if let Ok(client_encodings) = decode_header_value(client_header) {
-        if let Ok(mut accept_encoding) = AcceptEncoding::new(client_encodings) {
-            // Sort by client preference (highest quality first)
-            accept_encoding.sort_descending();
-
-            // Find the best match
-            let mut selected_encoding = None;
-            for (encoding, quality) in accept_encoding.items() {
-                if server_supported.contains(encoding) && *quality > 0.0 {
-                    selected_encoding = Some(encoding);
-                    break;
-                }
-            }
-
-            match selected_encoding {
-                Some(encoding) => {
-                    println!("   Selected encoding: {}", encoding);
-                    println!("   Server should use Content-Encoding: {}", encoding);
-                }
-                None => println!("   No acceptable encoding found"),
+    if let Ok(client_encodings) = decode_header_value(client_header)
+        && let Ok(accept_encoding) = AcceptEncoding::new(client_encodings)
+    {
+        // Negotiate following RFC 9110, see the `negotiation` module
+        match negotiate(&accept_encoding, &server_supported) {
+            NegotiationResult::Selected(encoding) => {
+                println!("   Selected encoding: {}", encoding);
+                println!("   Server should use Content-Encoding: {}", encoding);
             }
+            NegotiationResult::Identity => println!("   Selected encoding: identity"),
+            NegotiationResult::NotAcceptable => println!("   No acceptable encoding found"),
         }
     }
 }
@@ -0,0 +1,106 @@
+//! Encoding a response body with a `ContentCodec` for the negotiated encoding
+//!
+//! Run with `cargo run --example codecs --features codecs`.
+//!
+//! The crate only ships `IdentityCodec` so far. This example implements gzip
+//! with uncompressed ("stored") deflate blocks to stay dependency free; real code
+//! would wrap a compression library like `flate2` instead.
+
+use http_encoding_headers::{AcceptEncoding, ContentCodec, Encoding, NegotiationResult, codec_for};
+use std::io;
+
+/// Writes valid gzip streams made of stored deflate blocks, and reads them back
+struct StoredGzipCodec;
+
+impl ContentCodec for StoredGzipCodec {
+    fn encoding(&self) -> Encoding {
+        Encoding::Gzip
+    }
+
+    fn encode(&self, input: &[u8]) -> io::Result<Vec<u8>> {
+        // ID1, ID2, CM=deflate, no flags, no mtime, no extra flags, unknown OS
+        let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+        let mut blocks = input.chunks(u16::MAX as usize).peekable();
+        if blocks.peek().is_none() {
+            out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            let len = block.len() as u16;
+            out.push(u8::from(blocks.peek().is_none()));
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(&(!len).to_le_bytes());
+            out.extend_from_slice(block);
+        }
+        out.extend_from_slice(&crc32(input).to_le_bytes());
+        out.extend_from_slice(&(input.len() as u32).to_le_bytes());
+        Ok(out)
+    }
+
+    fn decode(&self, input: &[u8]) -> io::Result<Vec<u8>> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let body = match input {
+            [0x1f, 0x8b, 8, 0, _, _, _, _, _, _, body @ ..] if body.len() >= 8 => body,
+            _ => return Err(invalid("unsupported gzip header")),
+        };
+        let (mut blocks, trailer) = body.split_at(body.len() - 8);
+        let mut out = Vec::new();
+        loop {
+            let [header, l0, l1, _, _, rest @ ..] = blocks else {
+                return Err(invalid("truncated block"));
+            };
+            if header & 0b110 != 0 {
+                return Err(invalid("only stored blocks are supported"));
+            }
+            let len = u16::from_le_bytes([*l0, *l1]) as usize;
+            let data = rest.get(..len).ok_or_else(|| invalid("truncated block"))?;
+            out.extend_from_slice(data);
+            blocks = &rest[len..];
+            if header & 1 == 1 {
+                break;
+            }
+        }
+        if trailer[..4] != crc32(&out).to_le_bytes() {
+            return Err(invalid("checksum mismatch"));
+        }
+        Ok(out)
+    }
+}
+
+/// CRC-32 as used by gzip, computed bitwise
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn main() {
+    let codecs: Vec<Box<dyn ContentCodec>> =
+        vec![Box::new(StoredGzipCodec), codec_for(&Encoding::Identity).unwrap()];
+    let server: Vec<Encoding> = codecs.iter().map(|codec| codec.encoding()).collect();
+
+    let accept = AcceptEncoding::parse("br, gzip;q=0.8, identity;q=0.5").unwrap();
+    let body = b"Hello, World!";
+
+    let encoding = match accept.negotiate(&server) {
+        NegotiationResult::Selected(encoding) => encoding,
+        NegotiationResult::Identity => {
+            println!("No Content-Encoding header, body: {} bytes", body.len());
+            return;
+        }
+        NegotiationResult::NotAcceptable => {
+            println!("406 Not Acceptable");
+            return;
+        }
+    };
+
+    let codec = codecs.iter().find(|codec| codec.encoding() == encoding).unwrap();
+    let encoded = codec.encode(body).unwrap();
+    println!("Content-Encoding: {}", codec.encoding());
+    println!("Body: {} bytes, {} bytes encoded", body.len(), encoded.len());
+    assert_eq!(codec.decode(&encoded).unwrap(), body);
+}
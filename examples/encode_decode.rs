@@ -6,13 +6,15 @@
 //! - Error handling for invalid header values
 
 use http_encoding_headers::{
-    AcceptEncodingDecodeError, AcceptEncodingEncodeError, ContentEncoding, Encoding,
-    decode_header_value, encode_header_value,
+    AcceptEncodingDecodeError, AcceptEncodingEncodeError, Encoding, decode_header_value,
+    encode_header_value,
 };
 
 #[cfg(feature = "http_crates")]
 use headers::Header;
 #[cfg(feature = "http_crates")]
+use http_encoding_headers::ContentEncoding;
+#[cfg(feature = "http_crates")]
 use http::HeaderValue;
 
 fn main() {
@@ -129,17 +131,17 @@ fn content_encoding_examples() {
     ];
 
     for encoding in encodings_to_test {
-        let content_encoding = ContentEncoding::new(encoding);
+        let content_encoding = ContentEncoding::new(encoding).unwrap();
         println!("   Created: {:?}", content_encoding);
 
         // Encode to header value
         let mut values = Vec::new();
         content_encoding.encode(&mut values);
 
-        if let Some(header_value) = values.first() {
-            if let Ok(as_str) = header_value.to_str() {
-                println!("     Header value: {}", as_str);
-            }
+        if let Some(header_value) = values.first()
+            && let Ok(as_str) = header_value.to_str()
+        {
+            println!("     Header value: {}", as_str);
         }
     }
 
@@ -150,7 +152,7 @@ fn content_encoding_examples() {
     for header_str in test_headers {
         println!("   Decoding: \"{}\"", header_str);
 
-        let header_values = vec![HeaderValue::from_str(header_str).unwrap()];
+        let header_values = [HeaderValue::from_str(header_str).unwrap()];
         match ContentEncoding::decode(&mut header_values.iter()) {
             Ok(decoded) => {
                 println!("     Success: {:?}", decoded);
@@ -217,16 +219,20 @@ fn error_handling_examples() {
 
     #[cfg(feature = "http_crates")]
     {
-        // Example 3: Content-Encoding conflicting values
-        println!("\n3c. Content-Encoding conflicting values:");
-        let conflicting = vec![
-            HeaderValue::from_str("gzip").unwrap(),
-            HeaderValue::from_str("deflate").unwrap(),
-        ];
-
-        match ContentEncoding::decode(&mut conflicting.iter()) {
+        // Example 3: Content-Encoding without any encoding
+        println!("\n3c. Content-Encoding empty value:");
+        let empty = [HeaderValue::from_str("").unwrap()];
+
+        match ContentEncoding::decode(&mut empty.iter()) {
+            Ok(_) => println!("   Unexpected success"),
+            Err(_) => println!("   ✓ Correctly rejected empty Content-Encoding value"),
+        }
+
+        // Example 4: Content-Encoding wildcard
+        println!("\n3d. Content-Encoding wildcard:");
+        match ContentEncoding::new(Encoding::Wildcard) {
             Ok(_) => println!("   Unexpected success"),
-            Err(_) => println!("   ✓ Correctly rejected conflicting Content-Encoding values"),
+            Err(e) => println!("   ✓ Correctly rejected wildcard: {}", e),
         }
     }
 }
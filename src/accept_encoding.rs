@@ -1,5 +1,8 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use crate::encoding::{Encoding, QualityValue};
+use crate::encoding::{Encoding, QualityValue, is_token};
+use crate::negotiation::{self, NegotiationResult};
 use std::fmt::Write;
 use std::str::FromStr;
 use thiserror::Error;
@@ -10,18 +13,22 @@ use thiserror::Error;
 pub enum AcceptEncodingError {
     #[error("encodings cannot be empty")]
     EmptyEncodings,
+    #[error("non-standard encoding: {0}")]
+    NonStandardEncoding(String),
+    #[error("quality value must be within 0..=1, got {0}")]
+    InvalidQuality(QualityValue),
 }
 
 /// Represents an HTTP Accept-Encoding header with a list of supported encodings and their quality values
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct AcceptEncoding {
     encodings: Vec<(Encoding, QualityValue)>,
-    sort: Sort,
+    sort: SortState,
 }
 
 /// Sort state of encodings list by quality value
-#[derive(Clone)]
-enum Sort {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortState {
     Ascending,
     Descending,
     Unsorted,
@@ -29,56 +36,462 @@ enum Sort {
 
 impl AcceptEncoding {
     /// Creates a new `AcceptEncoding` from a vector of encodings with their quality values.
+    ///
+    /// Quality values must be within `0..=1`, NaN is rejected.
     pub fn new(encodings: Vec<(Encoding, QualityValue)>) -> Result<Self, AcceptEncodingError> {
         if encodings.is_empty() {
             return Err(AcceptEncodingError::EmptyEncodings);
         }
+        if let Some((_, q)) = encodings.iter().find(|(_, q)| !(0.0..=1.0).contains(q)) {
+            return Err(AcceptEncodingError::InvalidQuality(*q));
+        }
         Ok(Self {
             encodings,
-            sort: Sort::Unsorted,
+            sort: SortState::Unsorted,
         })
     }
 
+    /// Creates an `AcceptEncoding` accepting only identity: `identity;q=1, *;q=0`.
+    ///
+    /// This is what an empty Accept-Encoding header means per RFC 9110.
+    pub fn identity_only() -> Self {
+        Self::from_unchecked(vec![(Encoding::Identity, 1.0), (Encoding::Wildcard, 0.0)])
+    }
+
+    /// Creates the Accept-Encoding a server advertises for request bodies, e.g. in a
+    /// 415 response or to an `OPTIONS` request, accepting each of `encodings`
+    /// with `q=1`.
+    ///
+    /// Emit it with `to_advertised_header_value`.
+    pub fn advertise(encodings: &[Encoding]) -> Self {
+        Self::from_unchecked(encodings.iter().map(|enc| (enc.clone(), 1.0)).collect())
+    }
+
+    /// Encodes a server advertisement created with `advertise`.
+    ///
+    /// Unlike `to_header_value` an empty list is not an error but yields an empty
+    /// value, which tells the client that no content coding is accepted. Any other
+    /// error is returned.
+    pub fn to_advertised_header_value(&self) -> Result<String, AcceptEncodingEncodeError> {
+        match self.to_header_value() {
+            Err(AcceptEncodingEncodeError::EmptyEncodings) => Ok(String::new()),
+            result => result,
+        }
+    }
+
+    /// Parses an Accept-Encoding header value with RFC 9110 semantics.
+    ///
+    /// Unlike `decode_header_value`, an empty or whitespace-only value is not an error
+    /// but means that only identity is acceptable, yielding `identity_only`.
+    pub fn parse(value: &str) -> Result<Self, AcceptEncodingDecodeError> {
+        if value.trim().is_empty() {
+            return Ok(Self::identity_only());
+        }
+        Ok(Self::from_unchecked(decode_header_value(value)?))
+    }
+
+    /// Parses an Accept-Encoding header value leniently, collecting every deviation
+    /// from RFC 9110 instead of failing, e.g. for a header linter.
+    ///
+    /// Entries are decoded like `decode_header_value_relaxed`, a blank value is
+    /// identity only like in `parse`.
+    pub fn validate(value: &str) -> (Self, Vec<ComplianceWarning>) {
+        if value.trim().is_empty() {
+            return (Self::identity_only(), Vec::new());
+        }
+        let mut warnings = Vec::new();
+        let mut encodings: Vec<(Encoding, QualityValue)> = Vec::new();
+        for part in value.split(',') {
+            collect_segment_warnings(part, &mut warnings);
+            if let Some(entry) = decode_segment_relaxed(part) {
+                if encodings.iter().any(|(enc, _)| *enc == entry.0) {
+                    warnings.push(ComplianceWarning::DuplicateEncoding(entry.0.clone()));
+                }
+                encodings.push(entry);
+            }
+        }
+        (Self::from_unchecked(encodings), warnings)
+    }
+
+    /// Parses several Accept-Encoding header lines and combines them in order, like
+    /// decoding the header from a `HeaderMap` but without the `http_crates` feature.
+    ///
+    /// Blank lines are ignored and only blank lines mean identity only. Without any
+    /// lines the result is empty.
+    pub fn parse_multi<'a>(
+        values: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, AcceptEncodingDecodeError> {
+        Ok(decode_header_lines(values.into_iter().map(Ok))?
+            .unwrap_or_else(|| Self::from_unchecked(Vec::new())))
+    }
+
+    /// Creates a new unsorted `AcceptEncoding` without checking for emptiness.
+    pub(crate) fn from_unchecked(encodings: Vec<(Encoding, QualityValue)>) -> Self {
+        Self {
+            encodings,
+            sort: SortState::Unsorted,
+        }
+    }
+
+    /// Creates a new `AcceptEncoding` like `new`, but rejects `Encoding::Custom` entries.
+    ///
+    /// Meant for servers building the list from trusted data which only ever deal
+    /// with standard content codings.
+    pub fn new_standard_only(
+        encodings: Vec<(Encoding, QualityValue)>,
+    ) -> Result<Self, AcceptEncodingError> {
+        if let Some((custom, _)) = encodings
+            .iter()
+            .find(|(enc, _)| matches!(enc, Encoding::Custom(_)))
+        {
+            return Err(AcceptEncodingError::NonStandardEncoding(custom.to_string()));
+        }
+        Self::new(encodings)
+    }
+
     /// Returns a reference to the internal vector of encodings and their quality values.
     #[inline]
     pub fn items(&self) -> &[(Encoding, QualityValue)] {
         &self.encodings
     }
 
+    /// Returns true if `encoding` is explicitly listed, regardless of its quality value.
+    ///
+    /// Wildcard entries only match `Encoding::Wildcard` itself.
+    pub fn contains(&self, encoding: &Encoding) -> bool {
+        self.encodings.iter().any(|(enc, _)| enc == encoding)
+    }
+
+    /// Returns true if the client explicitly rejected `encoding`, either by listing it
+    /// with `q=0` or via a `*;q=0` wildcard entry when it is not listed itself.
+    pub fn rejects(&self, encoding: &Encoding) -> bool {
+        negotiation::effective_quality(self, encoding).is_some_and(|q| q <= 0.0)
+    }
+
+    /// Returns true if the client accepts encodings, but only legacy ones outside of
+    /// `modern`, e.g. `compress` or `x-compress` alone.
+    ///
+    /// Every entry with a positive quality value must be neither in `modern` nor
+    /// identity, and a positive `*` counts as accepting `modern`. Identity may still
+    /// be implicitly acceptable, check `negotiation::identity_acceptable` before
+    /// responding with 406 Not Acceptable.
+    pub fn only_legacy(&self, modern: &[Encoding]) -> bool {
+        let mut accepted = self.encodings.iter().filter(|(_, q)| *q > 0.0).peekable();
+        accepted.peek().is_some()
+            && accepted.all(|(enc, _)| match enc {
+                Encoding::Identity => false,
+                Encoding::Wildcard => modern.is_empty(),
+                enc => !modern.contains(enc),
+            })
+    }
+
+    /// Compares how much the client prefers `a` over `b`, e.g. `Ordering::Greater`
+    /// if `a` has the higher quality value.
+    ///
+    /// Quality values are resolved through the wildcard like in
+    /// `negotiation::effective_quality`. An encoding which is neither listed nor
+    /// covered by `*` ranks lowest, below encodings rejected with `q=0`.
+    pub fn prefers(&self, a: &Encoding, b: &Encoding) -> Ordering {
+        match (
+            negotiation::effective_quality(self, a),
+            negotiation::effective_quality(self, b),
+        ) {
+            (Some(q_a), Some(q_b)) => q_a.total_cmp(&q_b),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        }
+    }
+
+    /// Returns the encodings as a map from encoding to quality value.
+    ///
+    /// Duplicate entries are collapsed, keeping the highest quality value.
+    /// A wildcard entry (`*`) is included under `Encoding::Wildcard`.
+    pub fn as_map(&self) -> HashMap<Encoding, QualityValue> {
+        let mut map = HashMap::with_capacity(self.encodings.len());
+        for (enc, q) in &self.encodings {
+            map.entry(enc.clone())
+                .and_modify(|existing| {
+                    if *q > *existing {
+                        *existing = *q;
+                    }
+                })
+                .or_insert(*q);
+        }
+        map
+    }
+
+    /// Creates a new `AcceptEncoding` from `(token, quality)` pairs.
+    ///
+    /// Tokens are parsed like `Encoding::from_str`, so unknown tokens become
+    /// `Encoding::Custom`. This is the lowest common denominator for bridging
+    /// to other HTTP ecosystems.
+    pub fn from_pairs(pairs: Vec<(String, QualityValue)>) -> Result<Self, AcceptEncodingError> {
+        Self::new(
+            pairs
+                .into_iter()
+                // Infallible
+                .map(|(token, q)| (Encoding::from_str(&token).unwrap(), q))
+                .collect(),
+        )
+    }
+
+    /// Returns the encodings as `(token, quality)` pairs in storage order.
+    pub fn to_pairs(&self) -> Vec<(String, QualityValue)> {
+        self.encodings
+            .iter()
+            .map(|(enc, q)| (enc.to_string(), *q))
+            .collect()
+    }
+
+    /// Returns a copy with `server_default` appended at quality `q`, unless an
+    /// entry for it with a positive quality value already exists.
+    ///
+    /// This implements a graceful degradation policy for clients whose header
+    /// excludes everything the server supports. Note that the fallback is appended
+    /// even if the client explicitly rejected it with `q=0`.
+    pub fn with_server_fallback(
+        &self,
+        server_default: Encoding,
+        q: QualityValue,
+    ) -> AcceptEncoding {
+        let mut result = self.clone();
+        let acceptable = self
+            .encodings
+            .iter()
+            .any(|(enc, enc_q)| *enc == server_default && *enc_q > 0.0);
+        if !acceptable {
+            result.encodings.push((server_default, clamp_quality(q)));
+            result.sort = SortState::Unsorted;
+        }
+        result
+    }
+
+    /// Returns the current sort state of the encodings list.
+    #[inline]
+    pub fn sort_state(&self) -> SortState {
+        self.sort
+    }
+
+    /// Returns a copy sorted by quality value in descending order.
+    ///
+    /// The copy is flagged as `SortState::Descending`, so negotiation methods
+    /// like `preferred_allowed` take their sorted fast path on it.
+    pub fn to_sorted_descending(&self) -> AcceptEncoding {
+        let mut sorted = self.clone();
+        sorted.sort_descending();
+        sorted
+    }
+
+    /// Encodes the encodings as a header value in storage order
+    pub fn to_header_value(&self) -> Result<String, AcceptEncodingEncodeError> {
+        encode_header_value(&self.encodings)
+    }
+
+    /// Encodes the shortest header value expressing the same preferences.
+    ///
+    /// Two values are equivalent if every encoding, including identity and
+    /// encodings neither of them lists, gets the same quality value under the
+    /// rules of the `negotiation` module, where `q=0` and not listed are the same
+    /// except for identity. Entries are dropped as long as that holds:
+    ///
+    /// - duplicate entries of an encoding other than the highest one
+    /// - entries with the same quality value as `*`
+    /// - entries with `q=0` when there is no `*`, as unlisted encodings are not
+    ///   acceptable anyway. `identity;q=0` is kept, identity is acceptable by default
+    /// - `*;q=0` when identity is listed explicitly
+    ///
+    /// `q=1` is omitted and trailing zeros are trimmed as in `encode_header_value`.
+    /// A `*` is never introduced, as it would make unlisted encodings acceptable.
+    /// The remaining entries keep their order, and the last one is never dropped,
+    /// so e.g. `gzip;q=0` stays as is.
+    pub fn to_minimal_header_value(&self) -> Result<String, AcceptEncodingEncodeError> {
+        // Probes every listed encoding, identity and one encoding nobody lists
+        let unlisted = Encoding::Custom(String::new());
+        let probes: Vec<&Encoding> = self
+            .encodings
+            .iter()
+            .map(|(enc, _)| enc)
+            .filter(|enc| !matches!(enc, Encoding::Wildcard))
+            .chain([&Encoding::Identity, &unlisted])
+            .collect();
+        // Apart from identity, `q=0` and not being listed both mean not acceptable
+        let quality = |accept: &AcceptEncoding, enc: &Encoding| {
+            let q = negotiation::effective_quality(accept, enc);
+            if enc.is_identity() { q } else { q.filter(|q| *q > 0.0) }
+        };
+        let equivalent = |other: &AcceptEncoding| {
+            probes.iter().all(|enc| quality(self, enc) == quality(other, enc))
+        };
+
+        let mut minimal = self.clone();
+        let mut i = 0;
+        while i < minimal.encodings.len() && minimal.encodings.len() > 1 {
+            let removed = minimal.encodings.remove(i);
+            if !equivalent(&minimal) {
+                minimal.encodings.insert(i, removed);
+                i += 1;
+            }
+        }
+        minimal.to_header_value()
+    }
+
+    /// Encodes the encodings as a header value, highest quality value first.
+    ///
+    /// The encodings themselves are left in their current order.
+    pub fn to_header_value_sorted(&self) -> Result<String, AcceptEncodingEncodeError> {
+        self.to_sorted_descending().to_header_value()
+    }
+
     /// Sorts the encodings by quality value in descending order and returns self.
     pub fn sort_descending(&mut self) -> &mut Self {
         self.encodings.sort_by(|a, b| b.1.total_cmp(&a.1));
-        self.sort = Sort::Descending;
+        self.sort = SortState::Descending;
         self
     }
 
     /// Sorts the encodings by quality value in ascending order and returns self.
+    /// Ties end up in reverse listing order, so the earliest-listed entry is last.
     pub fn sort_ascending(&mut self) -> &mut Self {
-        self.encodings.sort_by(|a, b| a.1.total_cmp(&b.1));
-        self.sort = Sort::Ascending;
+        self.encodings.sort_by(|a, b| b.1.total_cmp(&a.1));
+        self.encodings.reverse();
+        self.sort = SortState::Ascending;
         self
     }
 
-    /// Returns the highest-preference encoding.
-    pub fn preferred(&self) -> Option<&Encoding> {
-        if self.encodings.is_empty() {
-            return None;
+    /// Sorts the encodings with a custom comparator and returns self.
+    ///
+    /// The sort state is set to `SortState::Unsorted`, as the resulting order is not
+    /// known to be by quality value.
+    pub fn sort_by<F>(&mut self, f: F) -> &mut Self
+    where
+        F: FnMut(&(Encoding, QualityValue), &(Encoding, QualityValue)) -> Ordering,
+    {
+        self.encodings.sort_by(f);
+        self.sort = SortState::Unsorted;
+        self
+    }
+
+    /// Adds `delta` to the quality value of every entry equal to `encoding` and
+    /// returns self, e.g. to boost a new encoding for A/B testing.
+    ///
+    /// The result is clamped to `0.0..=1.0`, so boosting zstd at q=0.7 by 0.5 yields
+    /// q=1.0. If `encoding` is not listed this is a no-op; wildcard entries are not
+    /// affected. Resets the sort state to `SortState::Unsorted`.
+    pub fn boost(&mut self, encoding: &Encoding, delta: QualityValue) -> &mut Self {
+        for (enc, q) in &mut self.encodings {
+            if enc == encoding {
+                *q = clamp_quality(*q + delta);
+                self.sort = SortState::Unsorted;
+            }
+        }
+        self
+    }
+
+    /// Appends an encoding with its quality value and returns self.
+    ///
+    /// `q` is clamped to `0..=1`, NaN counts as `0`. Resets the sort state to
+    /// `SortState::Unsorted`.
+    pub fn push(&mut self, encoding: Encoding, q: QualityValue) -> &mut Self {
+        self.encodings.push((encoding, clamp_quality(q)));
+        self.sort = SortState::Unsorted;
+        self
+    }
+
+    /// Keeps only the first `n` entries and returns self, preserving the sort state.
+    ///
+    /// Combined with `sort_descending` this keeps the top `n` client preferences.
+    /// Truncating to 0 leaves an empty list, for which `preferred` returns `None`
+    /// and encoding as a header emits no value at all.
+    pub fn truncate(&mut self, n: usize) -> &mut Self {
+        self.encodings.truncate(n);
+        self
+    }
+
+    /// Returns a copy without any entry for `encoding`, preserving the sort state.
+    ///
+    /// Removing the only remaining encoding leaves an empty list, for which
+    /// `preferred` returns `None`.
+    pub fn without(&self, encoding: &Encoding) -> AcceptEncoding {
+        AcceptEncoding {
+            encodings: self.encodings.iter().filter(|(e, _)| e != encoding).cloned().collect(),
+            sort: self.sort,
+        }
+    }
+
+    /// Iterates over all entries, highest quality value first, without mutating or
+    /// cloning.
+    ///
+    /// Uses the sort state: a descending list is iterated in order and an ascending
+    /// one in reverse. Only an unsorted list allocates, to sort the entry indices.
+    /// Unlike `preference_list`, duplicates, wildcards and `q=0` entries are kept.
+    pub fn iter_descending(&self) -> DescendingIter<'_> {
+        let order = match self.sort {
+            SortState::Descending => DescendingOrder::Forward(self.encodings.iter()),
+            SortState::Ascending => DescendingOrder::Reverse(self.encodings.iter().rev()),
+            SortState::Unsorted => {
+                let mut indices: Vec<usize> = (0..self.encodings.len()).collect();
+                indices.sort_by(|&a, &b| self.encodings[b].1.total_cmp(&self.encodings[a].1));
+                DescendingOrder::Indexed(&self.encodings, indices.into_iter())
+            }
+        };
+        DescendingIter { order }
+    }
+
+    /// Returns the final, clean list of acceptable encodings in descending
+    /// preference, without mutating or cloning.
+    ///
+    /// Duplicate entries collapse to their highest quality value. A wildcard entry is
+    /// expanded into the encodings of `universe` which are not listed explicitly, and
+    /// is not yielded itself. Encodings with `q=0` are left out. Equal quality values
+    /// keep the client's order, followed by the order of `universe`.
+    pub fn preference_list<'a>(&'a self, universe: &'a [Encoding]) -> PreferenceList<'a> {
+        let mut entries: Vec<(&'a Encoding, QualityValue)> = Vec::new();
+        for (enc, _) in self.iter_descending() {
+            if matches!(enc, Encoding::Wildcard) || entries.iter().any(|(seen, _)| *seen == enc) {
+                continue;
+            }
+            if let Some(q) = self.explicit_quality(enc) {
+                entries.push((enc, q));
+            }
+        }
+        if let Some(wildcard_q) = self.explicit_quality(&Encoding::Wildcard) {
+            for enc in universe {
+                if !matches!(enc, Encoding::Wildcard) && !self.contains(enc) {
+                    entries.push((enc, wildcard_q));
+                }
+            }
+        }
+
+        entries.retain(|(_, q)| *q > 0.0);
+        entries.sort_by(|(_, q_a), (_, q_b)| q_b.total_cmp(q_a));
+        PreferenceList {
+            entries: entries.into_iter(),
         }
-        let result = match self.sort {
-            Sort::Ascending => &self.encodings[self.encodings.len() - 1].0,
-            Sort::Descending => &self.encodings[0].0,
-            Sort::Unsorted => self
+    }
+
+    /// Returns the highest-preference acceptable encoding.
+    ///
+    /// Encodings with `q=0` are not acceptable and never returned, so `None` is
+    /// returned if every encoding is rejected. Ties go to the earliest-listed
+    /// encoding, so repeating an entry never changes the result.
+    pub fn preferred(&self) -> Option<&Encoding> {
+        let best = match self.sort {
+            SortState::Ascending => self.encodings.last(),
+            SortState::Descending => self.encodings.first(),
+            SortState::Unsorted => self
                 .encodings
                 .iter()
-                .max_by(|(_, weight1), (_, weight2)| weight1.total_cmp(weight2))
-                .map(|(encoding, _)| encoding)
-                .unwrap(),
+                .rev()
+                .max_by(|(_, weight1), (_, weight2)| weight1.total_cmp(weight2)),
         };
-        Some(result)
+        best.filter(|(_, q)| *q > 0.0).map(|(encoding, _)| encoding)
     }
 
     /// Returns the highest-preference encoding that is also present in `allowed`.
     /// Honors current sorting state (Ascending/Descending/Unsorted) like `preferred`.
+    ///
+    /// An `Encoding::Wildcard` in `allowed` allows every concrete encoding, so the
+    /// client's top acceptable encoding is returned. A client `*` covers every
+    /// allowed encoding the client does not list itself, like in `negotiate`.
     pub fn preferred_allowed<'a>(
         &'a self,
         allowed: impl Iterator<Item = &'a Encoding>,
@@ -86,49 +499,181 @@ impl AcceptEncoding {
         self.preferred_allowed_weighted(allowed.map(|e| (e, 1.0)))
     }
 
+    /// Like `preferred_allowed`, but takes owned encodings and returns an owned result,
+    /// so `allowed` can be computed on the fly.
+    pub fn preferred_allowed_owned(
+        &self,
+        allowed: impl IntoIterator<Item = Encoding>,
+    ) -> Option<Encoding> {
+        let allowed: Vec<Encoding> = allowed.into_iter().collect();
+        self.preferred_allowed(allowed.iter()).cloned()
+    }
+
+    /// Like `preferred_allowed`, but also returns the client's quality value
+    /// for the selected encoding.
+    pub fn preferred_allowed_with_quality<'a>(
+        &'a self,
+        allowed: impl Iterator<Item = &'a Encoding>,
+    ) -> Option<(&'a Encoding, QualityValue)> {
+        self.preferred_allowed_weighted_entry(allowed.map(|e| (e, 1.0)))
+    }
+
     /// Returns the highest-preference encoding that is also present in `allowed`,
     /// taking into account both client preferences and server weights.
     /// When multiple encodings have the same weight, the one with highest
     /// allowed weight is chosen.
+    ///
+    /// A weighted `Encoding::Wildcard` in `allowed` applies its weight to every
+    /// encoding not listed in `allowed` itself, e.g. `*` with `0.3` models a
+    /// proxy which can transcode to anything but prefers not to.
     pub fn preferred_allowed_weighted<'a>(
         &'a self,
         allowed: impl Iterator<Item=(&'a Encoding, QualityValue)>,
     ) -> Option<&'a Encoding> {
+        self.preferred_allowed_weighted_entry(allowed).map(|(enc, _)| enc)
+    }
+
+    /// Selects the content coding for a response against the encodings `server`
+    /// can produce, applying every RFC 9110 rule. This is what a server should call.
+    ///
+    /// - Encodings the client does not list take the quality value of `*`, if any.
+    /// - Encodings with `q=0`, explicitly or through `*;q=0`, are never selected.
+    /// - An `Encoding::Wildcard` in `server` stands for every encoding the client lists.
+    /// - The highest quality value wins, ties are broken by
+    ///   `Encoding::default_priority`, then by the order of `server`.
+    /// - Identity is implicitly acceptable unless rejected with `identity;q=0` or
+    ///   `*;q=0`, and is selected when no other encoding is acceptable.
+    /// - `NotAcceptable` means even identity was rejected, the server should
+    ///   respond with 406 Not Acceptable.
+    ///
+    /// The current sort state does not affect the result. Same as `negotiate`.
+    pub fn effective_preferred(&self, server: &[Encoding]) -> NegotiationResult {
+        negotiation::negotiate(self, server)
+    }
+
+    /// Negotiates the content coding to use for a response, following RFC 9110.
+    ///
+    /// See `negotiation::negotiate`.
+    pub fn negotiate(&self, server: &[Encoding]) -> NegotiationResult {
+        negotiation::negotiate(self, server)
+    }
+
+    /// Returns the content coding to use for a response, defaulting to identity.
+    ///
+    /// Identity is also returned if the client rejected it, see
+    /// `negotiation::best_or_identity`.
+    pub fn best_or_identity(&self, server: &[Encoding]) -> Encoding {
+        negotiation::best_or_identity(self, server)
+    }
+
+    /// Renders a multi-line, human-readable report of negotiating against `server`,
+    /// e.g. for a diagnostics endpoint.
+    ///
+    /// Lists every client entry, highest quality value first, with whether the
+    /// server supports it, followed by the outcome of `negotiate`. The format is
+    /// meant for humans and may change.
+    pub fn explain(&self, server: &[Encoding]) -> String {
+        let mut report = String::from("client preferences:\n");
+        for (enc, q) in self.iter_descending() {
+            let supported = server
+                .iter()
+                .any(|s| s.encoding_matches(enc) || enc.encoding_matches(s));
+            let support = if supported { "supported" } else { "not supported" };
+            let _ = writeln!(report, "  {enc} (q={q}) {support}");
+        }
+        let _ = match self.negotiate(server) {
+            NegotiationResult::Selected(enc) => write!(report, "selected: {enc}"),
+            NegotiationResult::Identity => write!(report, "selected: identity"),
+            NegotiationResult::NotAcceptable => {
+                write!(report, "selected: none, respond with 406 Not Acceptable")
+            }
+        };
+        report
+    }
+
+    /// Like `negotiate`, but with a server-side weight per encoding.
+    ///
+    /// See `negotiation::negotiate_weighted`.
+    pub fn negotiate_weighted(&self, server: &[(Encoding, QualityValue)]) -> NegotiationResult {
+        negotiation::negotiate_weighted(self, server)
+    }
+
+    /// Returns every acceptable encoding of `server` with its effective score,
+    /// best first, exactly as `negotiate_weighted` ranks them.
+    ///
+    /// See `negotiation::scores`.
+    pub fn scores(&self, server: &[(Encoding, QualityValue)]) -> Vec<(Encoding, QualityValue)> {
+        negotiation::scores(self, server)
+    }
+
+    /// Selects the best encoding of `server` like `scores`, adding `bias_bonus` to the
+    /// score of `bias` first.
+    ///
+    /// See `negotiation::preferred_allowed_biased`.
+    pub fn preferred_allowed_biased(
+        &self,
+        server: &[(Encoding, QualityValue)],
+        bias: &Encoding,
+        bias_bonus: QualityValue,
+    ) -> Option<Encoding> {
+        negotiation::preferred_allowed_biased(self, server, bias, bias_bonus)
+    }
+
+    /// Returns the highest quality value explicitly assigned to `encoding`.
+    pub(crate) fn explicit_quality(&self, encoding: &Encoding) -> Option<QualityValue> {
+        self.encodings
+            .iter()
+            .filter(|(enc, _)| enc == encoding)
+            .map(|(_, q)| *q)
+            .max_by(QualityValue::total_cmp)
+    }
+
+    /// Selects the encoding and client quality value used by `preferred_allowed_weighted`.
+    fn preferred_allowed_weighted_entry<'a>(
+        &'a self,
+        allowed: impl Iterator<Item = (&'a Encoding, QualityValue)>,
+    ) -> Option<(&'a Encoding, QualityValue)> {
         if self.encodings.is_empty() {
             return None;
         }
 
-        let allowed_map: HashMap<&Encoding, QualityValue> = allowed.collect();
+        let allowed: Vec<(&'a Encoding, QualityValue)> = allowed.collect();
+        let allowed_map: HashMap<&Encoding, QualityValue> = allowed.iter().copied().collect();
+        // A server wildcard allows every concrete encoding it does not list itself
+        let allowed_weight = |enc: &Encoding| match allowed_map.get(enc) {
+            Some(&allowed_q) => Some(allowed_q),
+            None if matches!(enc, Encoding::Wildcard) => None,
+            None => allowed_map.get(&Encoding::Wildcard).copied(),
+        };
+        // A client wildcard stands for every allowed encoding the client does not list
+        let unlisted: Vec<(&'a Encoding, QualityValue)> = allowed
+            .iter()
+            .filter(|(enc, _)| {
+                !matches!(enc, Encoding::Wildcard) && self.explicit_quality(enc).is_none()
+            })
+            .map(|(enc, _)| (*enc, allowed_map[enc]))
+            .collect();
+
+        // Turns each client entry into (encoding, client q, allowed weight) candidates
+        let (allowed_weight, unlisted) = (&allowed_weight, &unlisted);
+        let candidates = move |(enc, client_q): &'a (Encoding, QualityValue)| {
+            let listed = allowed_weight(enc).map(|allowed_q| (enc, *client_q, allowed_q));
+            let covered = unlisted
+                .iter()
+                .filter(move |_| matches!(enc, Encoding::Wildcard))
+                .map(move |(covered, allowed_q)| (*covered, *client_q, *allowed_q));
+            listed.into_iter().chain(covered)
+        };
 
         // Fast path when already sorted
         match self.sort {
-            Sort::Descending => {
-                // Search from start until we find an allowed encoding
-                for (enc, q) in &self.encodings {
-                    if *q > 0.0 {
-                        if let Some(allowed_q) = allowed_map.get(enc) {
-                            if *allowed_q > 0.0 {
-                                return Some(enc);
-                            }
-                        }
-                    }
-                }
-                None
-            }
-            Sort::Ascending => {
-                // Search from end until we find an allowed encoding
-                for (enc, q) in self.encodings.iter().rev() {
-                    if *q > 0.0 {
-                        if let Some(allowed_q) = allowed_map.get(enc) {
-                            if *allowed_q > 0.0 {
-                                return Some(enc);
-                            }
-                        }
-                    }
-                }
-                None
+            SortState::Descending => best_of_descending(self.encodings.iter().flat_map(candidates)),
+            SortState::Ascending => {
+                // Ties are stored in reverse listing order, so searching from the
+                // end keeps the client's order
+                best_of_descending(self.encodings.iter().rev().flat_map(candidates))
             }
-            Sort::Unsorted => {
+            SortState::Unsorted => {
                 // self.encodings has preference order. We only use allowed weights
                 // to break ties among encodings that share the same max client quality.
                 // 1) Find the maximum client quality among encodings that are allowed (>0).
@@ -137,53 +682,163 @@ impl AcceptEncoding {
 
                 // Find max client quality among allowed encodings (>0 both sides)
                 let mut max_client_q: Option<QualityValue> = None;
-                for (enc, client_q) in &self.encodings {
-                    if *client_q <= 0.0 {
+                for (_, client_q, allowed_q) in self.encodings.iter().flat_map(candidates) {
+                    if client_q <= 0.0 {
                         continue;
                     }
-                    if let Some(&allowed_q) = allowed_map.get(enc) {
-                        if allowed_q <= 0.0 {
-                            continue;
-                        }
+                    if allowed_q > 0.0 {
                         match max_client_q {
-                            None => max_client_q = Some(*client_q),
-                            Some(curr_max) if client_q > &curr_max => max_client_q = Some(*client_q),
+                            None => max_client_q = Some(client_q),
+                            Some(curr_max) if client_q > curr_max => max_client_q = Some(client_q),
                             _ => {}
                         }
                     }
                 }
 
-                let Some(target_q) = max_client_q else {
-                    return None;
-                };
+                let target_q = max_client_q?;
 
                 // Among entries with client_q == target_q and allowed (>0), choose the one
                 // with the highest allowed weight. Preserve self.encodings order when allowed
                 // weights tie, thus keeping self.encodings preference.
-                let mut best_enc: Option<&Encoding> = None;
+                let mut best_entry: Option<(&Encoding, QualityValue)> = None;
                 let mut best_allowed_q: QualityValue = 0.0;
 
-                for (enc, client_q) in &self.encodings {
-                    if *client_q != target_q {
+                for (enc, client_q, allowed_q) in self.encodings.iter().flat_map(candidates) {
+                    if client_q != target_q {
                         continue;
                     }
-                    if let Some(&allowed_q) = allowed_map.get(enc) {
-                        if allowed_q <= 0.0 {
-                            continue;
-                        }
-                        if best_enc.is_none() || allowed_q > best_allowed_q {
-                            best_enc = Some(enc);
-                            best_allowed_q = allowed_q;
-                        }
+                    if allowed_q > 0.0 && (best_entry.is_none() || allowed_q > best_allowed_q) {
+                        best_entry = Some((enc, client_q));
+                        best_allowed_q = allowed_q;
                     }
                 }
 
-                best_enc
+                best_entry
             }
         }
     }
 }
 
+/// Selects the entry for `preferred_allowed_weighted` from `(encoding, client q,
+/// allowed weight)` candidates ordered by descending client quality value.
+///
+/// Stops after the candidates sharing the quality value of the first allowed one and
+/// picks the one with the highest allowed weight among them, like the unsorted path.
+fn best_of_descending<'a>(
+    candidates: impl Iterator<Item = (&'a Encoding, QualityValue, QualityValue)>,
+) -> Option<(&'a Encoding, QualityValue)> {
+    let mut best: Option<((&'a Encoding, QualityValue), QualityValue)> = None;
+    for (enc, q, allowed_q) in candidates {
+        if q <= 0.0 || best.is_some_and(|((_, best_q), _)| q < best_q) {
+            break;
+        }
+        if allowed_q > 0.0 && best.is_none_or(|(_, best_allowed_q)| allowed_q > best_allowed_q) {
+            best = Some(((enc, q), allowed_q));
+        }
+    }
+    best.map(|(entry, _)| entry)
+}
+
+/// Represents a modern browser accepting `gzip, deflate, br, zstd`, all at q=1.0
+impl Default for AcceptEncoding {
+    fn default() -> Self {
+        Self::from_unchecked(vec![
+            (Encoding::Gzip, 1.0),
+            (Encoding::Deflate, 1.0),
+            (Encoding::Br, 1.0),
+            (Encoding::Zstd, 1.0),
+        ])
+    }
+}
+
+/// Borrowing iterator over the acceptable encodings of an `AcceptEncoding` in
+/// descending preference, created by `AcceptEncoding::preference_list`
+#[derive(Debug, Clone)]
+pub struct PreferenceList<'a> {
+    entries: std::vec::IntoIter<(&'a Encoding, QualityValue)>,
+}
+
+impl<'a> Iterator for PreferenceList<'a> {
+    type Item = (&'a Encoding, QualityValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl ExactSizeIterator for PreferenceList<'_> {}
+
+/// Borrowing iterator over all entries of an `AcceptEncoding`, highest quality
+/// value first, created by `AcceptEncoding::iter_descending`
+#[derive(Debug, Clone)]
+pub struct DescendingIter<'a> {
+    order: DescendingOrder<'a>,
+}
+
+#[derive(Debug, Clone)]
+enum DescendingOrder<'a> {
+    Forward(std::slice::Iter<'a, (Encoding, QualityValue)>),
+    Reverse(std::iter::Rev<std::slice::Iter<'a, (Encoding, QualityValue)>>),
+    Indexed(&'a [(Encoding, QualityValue)], std::vec::IntoIter<usize>),
+}
+
+impl<'a> Iterator for DescendingIter<'a> {
+    type Item = (&'a Encoding, QualityValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (enc, q) = match &mut self.order {
+            DescendingOrder::Forward(entries) => entries.next()?,
+            DescendingOrder::Reverse(entries) => entries.next()?,
+            DescendingOrder::Indexed(entries, indices) => &entries[indices.next()?],
+        };
+        Some((enc, *q))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.order {
+            DescendingOrder::Forward(entries) => entries.size_hint(),
+            DescendingOrder::Reverse(entries) => entries.size_hint(),
+            DescendingOrder::Indexed(_, indices) => indices.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for DescendingIter<'_> {}
+
+impl TryFrom<Vec<(String, QualityValue)>> for AcceptEncoding {
+    type Error = AcceptEncodingError;
+
+    fn try_from(pairs: Vec<(String, QualityValue)>) -> Result<Self, Self::Error> {
+        AcceptEncoding::from_pairs(pairs)
+    }
+}
+
+/// Appends all entries, resetting the sort state to `SortState::Unsorted` like `push`
+/// Quality values are clamped like in `AcceptEncoding::push`.
+impl Extend<(Encoding, QualityValue)> for AcceptEncoding {
+    fn extend<T: IntoIterator<Item = (Encoding, QualityValue)>>(&mut self, iter: T) {
+        self.encodings
+            .extend(iter.into_iter().map(|(encoding, q)| (encoding, clamp_quality(q))));
+        self.sort = SortState::Unsorted;
+    }
+}
+
+impl From<&AcceptEncoding> for Vec<(String, QualityValue)> {
+    fn from(accept_encoding: &AcceptEncoding) -> Self {
+        accept_encoding.to_pairs()
+    }
+}
+
+impl From<AcceptEncoding> for Vec<(String, QualityValue)> {
+    fn from(accept_encoding: AcceptEncoding) -> Self {
+        accept_encoding.to_pairs()
+    }
+}
+
 #[cfg(feature = "http_crates")]
 impl headers::Header for AcceptEncoding {
     fn name() -> &'static headers::HeaderName {
@@ -195,20 +850,11 @@ impl headers::Header for AcceptEncoding {
         Self: Sized,
         I: Iterator<Item = &'i headers::HeaderValue>,
     {
-        let mut all_parsed: Vec<(Encoding, QualityValue)> = Vec::new();
-
-        for header_value in values {
-            let parsed = header_value
-                .to_str()
-                .map_err(|_| headers::Error::invalid())
-                .and_then(|v| decode_header_value(v).map_err(|_| headers::Error::invalid()))?;
-            all_parsed.extend(parsed);
+        match decode_http_header_values(values) {
+            Ok(Some(accept_encoding)) => Ok(accept_encoding),
+            Ok(None) => Ok(AcceptEncoding::from_unchecked(Vec::new())),
+            Err(_) => Err(headers::Error::invalid()),
         }
-
-        Ok(AcceptEncoding {
-            encodings: all_parsed,
-            sort: Sort::Unsorted,
-        })
     }
 
     fn encode<E: Extend<headers::HeaderValue>>(&self, values: &mut E) {
@@ -222,6 +868,109 @@ impl headers::Header for AcceptEncoding {
     }
 }
 
+/// Decodes all lines of an Accept-Encoding header with the semantics of
+/// `AcceptEncoding::parse`, reporting non visible ASCII values as invalid tokens.
+#[cfg(feature = "http_crates")]
+fn decode_http_header_values<'i>(
+    values: impl Iterator<Item = &'i http::HeaderValue>,
+) -> Result<Option<AcceptEncoding>, AcceptEncodingDecodeError> {
+    decode_header_lines(values.map(|header_value| {
+        header_value.to_str().map_err(|_| {
+            AcceptEncodingDecodeError::InvalidToken(
+                String::from_utf8_lossy(header_value.as_bytes()).into_owned(),
+            )
+        })
+    }))
+}
+
+/// Decodes all lines of an Accept-Encoding header with the semantics of
+/// `AcceptEncoding::parse`.
+///
+/// Blank lines are ignored, a header consisting only of blank lines is identity
+/// only. Returns `None` if there are no lines at all.
+fn decode_header_lines<'i>(
+    values: impl Iterator<Item = Result<&'i str, AcceptEncodingDecodeError>>,
+) -> Result<Option<AcceptEncoding>, AcceptEncodingDecodeError> {
+    let mut present = false;
+    let mut all_parsed: Vec<(Encoding, QualityValue)> = Vec::new();
+
+    for value in values {
+        present = true;
+        let value = value?;
+        if !value.trim().is_empty() {
+            all_parsed.extend(decode_header_value(value)?);
+        }
+    }
+
+    Ok(match (present, all_parsed.is_empty()) {
+        (false, _) => None,
+        (true, true) => Some(AcceptEncoding::identity_only()),
+        (true, false) => Some(AcceptEncoding::from_unchecked(all_parsed)),
+    })
+}
+
+/// Decodes a single header line like `AcceptEncoding::parse`
+#[cfg(feature = "http_crates")]
+impl TryFrom<&http::HeaderValue> for AcceptEncoding {
+    type Error = AcceptEncodingDecodeError;
+
+    fn try_from(header_value: &http::HeaderValue) -> Result<Self, Self::Error> {
+        // A single value always yields a result
+        Ok(decode_http_header_values(std::iter::once(header_value))?.unwrap())
+    }
+}
+
+/// Rejection for extracting an `AcceptEncoding` from request headers, e.g. in a web
+/// framework extractor
+#[cfg(feature = "http_crates")]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AcceptEncodingRejection {
+    #[error("missing Accept-Encoding header")]
+    Missing,
+    #[error("invalid Accept-Encoding header: {0}")]
+    Invalid(#[from] AcceptEncodingDecodeError),
+}
+
+#[cfg(feature = "http_crates")]
+impl AcceptEncodingRejection {
+    /// Returns the HTTP status to respond with, which is always `400 Bad Request`
+    pub fn status(&self) -> http::StatusCode {
+        http::StatusCode::BAD_REQUEST
+    }
+}
+
+#[cfg(feature = "http_crates")]
+impl AcceptEncoding {
+    /// Extracts the Accept-Encoding header from request headers, combining
+    /// multiple header lines.
+    ///
+    /// A missing header is an error; callers treating the header as optional can
+    /// match on `AcceptEncodingRejection::Missing`. A present but empty header means
+    /// identity only, see `AcceptEncoding::parse`.
+    pub fn from_headers(headers: &http::HeaderMap) -> Result<Self, AcceptEncodingRejection> {
+        decode_http_header_values(headers.get_all(http::header::ACCEPT_ENCODING).iter())?
+            .ok_or(AcceptEncodingRejection::Missing)
+    }
+
+    /// Encodes and inserts the header under `ACCEPT_ENCODING`, replacing all existing
+    /// values, e.g. for a proxy rewriting the header before forwarding.
+    ///
+    /// An empty list removes the header instead. On any other error the map is left
+    /// untouched, as dropping the header would make every coding acceptable.
+    pub fn insert_into(&self, map: &mut http::HeaderMap) -> Result<(), AcceptEncodingEncodeError> {
+        if self.encodings.is_empty() {
+            map.remove(http::header::ACCEPT_ENCODING);
+            return Ok(());
+        }
+        let encoded = encode_header_value(&self.encodings)?;
+        let value = http::HeaderValue::from_str(&encoded)
+            .map_err(|_| AcceptEncodingEncodeError::InvalidHeaderValue(encoded))?;
+        map.insert(http::header::ACCEPT_ENCODING, value);
+        Ok(())
+    }
+}
+
 /// Error types for Accept-Encoding header value decoding
 #[derive(Error, Debug)]
 #[non_exhaustive]
@@ -234,251 +983,2047 @@ pub enum AcceptEncodingDecodeError {
     InvalidQualityValue(String),
     #[error("unknown directive: {0}")]
     UnexpectedDirective(String),
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    #[error("duplicate quality value for encoding: {0}")]
+    DuplicateQuality(String),
+    #[error("empty parameter for encoding: {0}")]
+    EmptyParameter(String),
 }
 
-/// Decodes Accept-Encoding header value into a list of encodings with quality values
-pub fn decode_header_value(
-    value: &str,
-) -> Result<Vec<(Encoding, QualityValue)>, AcceptEncodingDecodeError> {
-    let mut parsed: Vec<(Encoding, QualityValue)> = vec![];
-    for part in value.split(',') {
-        let part = part.trim();
-        if part.is_empty() {
-            return Err(AcceptEncodingDecodeError::EmptyEncodingWeightTuple);
-        }
-
-        let mut it = part.split(';');
-        let enc = it.next().map(str::trim).unwrap_or_default();
-        if enc.is_empty() {
-            return Err(AcceptEncodingDecodeError::EmptyEncodingName);
-        }
-
-        let mut q: QualityValue = 1.0;
-        for p in it {
-            let p = p.trim();
-            if let Some(v) = p.strip_prefix("q=") {
-                // RFC allows up to three decimals, we allow more
-                q = v
-                    .parse::<QualityValue>()
-                    .map_err(|_| AcceptEncodingDecodeError::InvalidQualityValue(v.to_string()))?;
-            } else if !p.is_empty() {
-                // There is some unknown data where only a quality value
-                // is expected
-                return Err(AcceptEncodingDecodeError::UnexpectedDirective(
-                    p.to_string(),
-                ));
-            }
-        }
+/// A deviation from RFC 9110 found by `AcceptEncoding::validate`
+///
+/// Each warning names the offending part of the header value and how it was
+/// handled.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ComplianceWarning {
+    #[error("empty list element ignored")]
+    EmptyElement,
+    #[error("entry without encoding name dropped")]
+    EmptyEncodingName,
+    #[error("control characters, entry dropped: {0:?}")]
+    ForbiddenCharacters(String),
+    #[error("encoding is not a valid token: {0}")]
+    InvalidToken(String),
+    #[error("empty parameter ignored for encoding: {0}")]
+    EmptyParameter(String),
+    #[error("unknown parameter ignored: {0}")]
+    UnknownParameter(String),
+    #[error("duplicate quality value, the last one wins for encoding: {0}")]
+    DuplicateQuality(String),
+    #[error("unparsable quality value, entry dropped: {0}")]
+    UnparsableQuality(String),
+    #[error("quality value out of range, clamped: {0}")]
+    QualityOutOfRange(String),
+    #[error("quality value with more than 3 decimals: {0}")]
+    TooManyDecimals(String),
+    #[error("quality value does not follow the qvalue grammar: {0}")]
+    NonStandardQuality(String),
+    #[error("duplicate encoding: {0}")]
+    DuplicateEncoding(Encoding),
+    #[error("obsolete line folding replaced with a space")]
+    ObsoleteLineFolding,
+}
+
+/// Appends the `ComplianceWarning`s of a single segment, mirroring what
+/// `decode_segment_relaxed` tolerates
+fn collect_segment_warnings(part: &str, warnings: &mut Vec<ComplianceWarning>) {
+    let part = unfold(part);
+    if matches!(part, Cow::Owned(_)) {
+        warnings.push(ComplianceWarning::ObsoleteLineFolding);
+    }
+    if part.chars().any(is_forbidden_char) {
+        warnings.push(ComplianceWarning::ForbiddenCharacters(part.to_string()));
+        return;
+    }
+    if part.trim().is_empty() {
+        warnings.push(ComplianceWarning::EmptyElement);
+        return;
+    }
 
-        // Infallible
-        parsed.push((Encoding::from_str(enc).unwrap(), q));
+    let mut it = part.split(';');
+    let enc = it.next().map(str::trim).unwrap_or_default();
+    if enc.is_empty() {
+        warnings.push(ComplianceWarning::EmptyEncodingName);
+        return;
+    }
+    if !is_token(enc) {
+        warnings.push(ComplianceWarning::InvalidToken(enc.to_string()));
     }
 
-    Ok(parsed)
+    let mut seen_q = false;
+    for p in it {
+        let p = p.trim();
+        let Some((name, v)) = p.split_once('=') else {
+            warnings.push(if p.is_empty() {
+                ComplianceWarning::EmptyParameter(enc.to_string())
+            } else {
+                ComplianceWarning::UnknownParameter(p.to_string())
+            });
+            continue;
+        };
+        if !name.trim().eq_ignore_ascii_case("q") {
+            warnings.push(ComplianceWarning::UnknownParameter(p.to_string()));
+            continue;
+        }
+        if seen_q {
+            warnings.push(ComplianceWarning::DuplicateQuality(enc.to_string()));
+        }
+        seen_q = true;
+
+        let v = v.trim();
+        match v.trim_matches('"').parse::<QualityValue>() {
+            Ok(q) if q.is_nan() => {
+                warnings.push(ComplianceWarning::UnparsableQuality(v.to_string()));
+            }
+            Err(_) => warnings.push(ComplianceWarning::UnparsableQuality(v.to_string())),
+            Ok(q) if !(0.0..=1.0).contains(&q) => {
+                warnings.push(ComplianceWarning::QualityOutOfRange(v.to_string()));
+            }
+            Ok(_) if v.split_once('.').is_some_and(|(_, frac)| frac.len() > 3) => {
+                warnings.push(ComplianceWarning::TooManyDecimals(v.to_string()));
+            }
+            Ok(_) if name != "q" || !is_qvalue(v) => {
+                warnings.push(ComplianceWarning::NonStandardQuality(p.to_string()));
+            }
+            Ok(_) => {}
+        }
+    }
 }
 
-/// Error type for Accept-Encoding header value encoding
-#[derive(Error, Debug)]
-#[non_exhaustive]
-pub enum AcceptEncodingEncodeError {
-    #[error("encodings cannot be empty")]
-    EmptyEncodings,
+/// Strictness of Accept-Encoding header value decoding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecodeMode {
+    /// Accepts malformed input as long as it can be interpreted, e.g. encodings
+    /// which are not valid tokens become `Encoding::Custom`
+    #[default]
+    Lenient,
+    /// Rejects input which does not conform to the RFC 9110 grammar, e.g. repeated
+    /// `q` parameters, which lenient decoding resolves by letting the last one win
+    Strict,
 }
 
-/// Encodes a list of encodings with quality values into Accept-Encoding header value
-pub fn encode_header_value(
+/// Decodes Accept-Encoding header value into a list of encodings with quality values
+pub fn decode_header_value(
+    value: &str,
+) -> Result<Vec<(Encoding, QualityValue)>, AcceptEncodingDecodeError> {
+    decode_header_value_with_mode(value, DecodeMode::Lenient)
+}
+
+/// Decodes Accept-Encoding header value into a list of encodings with quality values,
+/// using the given decode mode
+pub fn decode_header_value_with_mode(
+    value: &str,
+    mode: DecodeMode,
+) -> Result<Vec<(Encoding, QualityValue)>, AcceptEncodingDecodeError> {
+    decode_segments(value, mode, 1.0)
+}
+
+/// Decodes Accept-Encoding header value like `decode_header_value`, but into `out`,
+/// replacing its previous contents.
+///
+/// Reusing the same `out` for many decodes avoids allocating once its capacity
+/// suffices, e.g. with a per-worker buffer. On error `out` holds the entries
+/// decoded before the malformed one.
+pub fn decode_header_value_into(
+    value: &str,
+    out: &mut Vec<(Encoding, QualityValue)>,
+) -> Result<(), AcceptEncodingDecodeError> {
+    out.clear();
+    decode_segments_into(value, DecodeMode::Lenient, 1.0, out)
+}
+
+/// Decodes Accept-Encoding header value on a best-effort basis, never erroring.
+///
+/// Meant for servers which rather serve something than reject broken clients.
+/// On top of what `decode_header_value` accepts, it tolerates:
+///
+/// - empty segments, e.g. from repeated or trailing commas, which are skipped
+/// - whitespace anywhere around names, `;` and `=`
+/// - an uppercase `Q=` parameter name
+/// - quoted q-values like `q="0.5"` and leading-dot q-values like `q=.5`
+/// - out-of-range q-values, which are clamped to `0.0..=1.0`
+/// - unknown parameters, which are ignored
+///
+/// Segments that cannot be interpreted at all are dropped: empty encoding names,
+/// unparsable or NaN q-values, and segments containing control characters.
+pub fn decode_header_value_relaxed(value: &str) -> Vec<(Encoding, QualityValue)> {
+    value.split(',').filter_map(decode_segment_relaxed).collect()
+}
+
+/// Decodes a single segment for `decode_header_value_relaxed`
+fn decode_segment_relaxed(part: &str) -> Option<(Encoding, QualityValue)> {
+    let part = unfold(part);
+    if part.chars().any(is_forbidden_char) {
+        return None;
+    }
+
+    let mut it = part.split(';');
+    let enc = it.next().map(str::trim).unwrap_or_default();
+    if enc.is_empty() {
+        return None;
+    }
+
+    let mut q: QualityValue = 1.0;
+    for p in it {
+        let Some((name, v)) = p.split_once('=') else {
+            continue;
+        };
+        if name.trim().eq_ignore_ascii_case("q") {
+            let v = v.trim().trim_matches('"');
+            q = v.parse::<QualityValue>().ok().filter(|q| !q.is_nan())?;
+        }
+    }
+
+    // Infallible
+    Some((Encoding::from_str(enc).unwrap(), q.clamp(0.0, 1.0)))
+}
+
+/// Decodes Accept-Encoding header value like `decode_header_value`, but entries
+/// without a `q` parameter get `default` instead of the RFC default of 1.0
+pub fn decode_header_value_with_default_q(
+    value: &str,
+    default: QualityValue,
+) -> Result<Vec<(Encoding, QualityValue)>, AcceptEncodingDecodeError> {
+    decode_segments(value, DecodeMode::Lenient, default)
+}
+
+/// Decodes all comma-separated segments of an Accept-Encoding header value.
+///
+/// The result is pre-sized by the number of commas, so decoding allocates the list
+/// exactly once instead of growing it entry by entry.
+fn decode_segments(
+    value: &str,
+    mode: DecodeMode,
+    default_q: QualityValue,
+) -> Result<Vec<(Encoding, QualityValue)>, AcceptEncodingDecodeError> {
+    let mut encodings = Vec::new();
+    decode_segments_into(value, mode, default_q, &mut encodings)?;
+    Ok(encodings)
+}
+
+/// Appends all comma-separated segments of an Accept-Encoding header value to
+/// `out`, reserving room for all of them up front
+fn decode_segments_into(
+    value: &str,
+    mode: DecodeMode,
+    default_q: QualityValue,
+    out: &mut Vec<(Encoding, QualityValue)>,
+) -> Result<(), AcceptEncodingDecodeError> {
+    out.reserve(value.bytes().filter(|&b| b == b',').count() + 1);
+    for part in value.split(',') {
+        out.push(decode_segment(part, mode, default_q)?);
+    }
+    Ok(())
+}
+
+/// Decodes a single comma-separated segment of an Accept-Encoding header value
+fn decode_segment(
+    part: &str,
+    mode: DecodeMode,
+    default_q: QualityValue,
+) -> Result<(Encoding, QualityValue), AcceptEncodingDecodeError> {
+    // Control characters and stray byte order marks are never valid, no matter
+    // how lenient the mode is. Line folding left by old intermediaries is whitespace.
+    let part = unfold(part);
+    if part.chars().any(is_forbidden_char) {
+        return Err(AcceptEncodingDecodeError::InvalidToken(part.into_owned()));
+    }
+
+    let part = part.trim();
+    if part.is_empty() {
+        return Err(AcceptEncodingDecodeError::EmptyEncodingWeightTuple);
+    }
+
+    let mut it = part.split(';');
+    let enc = it.next().map(str::trim).unwrap_or_default();
+    if enc.is_empty() {
+        return Err(AcceptEncodingDecodeError::EmptyEncodingName);
+    }
+    if mode == DecodeMode::Strict && !is_token(enc) {
+        return Err(AcceptEncodingDecodeError::InvalidToken(enc.to_string()));
+    }
+
+    let mut q = default_q;
+    let mut seen_q = false;
+    for p in it {
+        let p = p.trim();
+        if let Some(v) = p.strip_prefix("q=") {
+            // Lenient mode lets the last quality value win
+            if mode == DecodeMode::Strict && seen_q {
+                return Err(AcceptEncodingDecodeError::DuplicateQuality(enc.to_string()));
+            }
+            seen_q = true;
+            if mode == DecodeMode::Strict && !is_qvalue(v) {
+                return Err(AcceptEncodingDecodeError::InvalidQualityValue(
+                    v.to_string(),
+                ));
+            }
+            // RFC allows up to three decimals, we allow more in lenient mode and
+            // clamp values outside of 0..=1
+            q = v
+                .parse::<QualityValue>()
+                .ok()
+                .filter(|q| !q.is_nan())
+                .ok_or_else(|| AcceptEncodingDecodeError::InvalidQualityValue(v.to_string()))?
+                .clamp(0.0, 1.0);
+        } else if p.is_empty() {
+            // `;;` or a trailing `;` is malformed, lenient mode skips it
+            if mode == DecodeMode::Strict {
+                return Err(AcceptEncodingDecodeError::EmptyParameter(enc.to_string()));
+            }
+        } else {
+            // There is some unknown data where only a quality value
+            // is expected
+            return Err(AcceptEncodingDecodeError::UnexpectedDirective(
+                p.to_string(),
+            ));
+        }
+    }
+
+    // Infallible
+    Ok((Encoding::from_str(enc).unwrap(), q))
+}
+
+/// Incremental Accept-Encoding header value parser for values arriving in fragments
+///
+/// Complete comma-separated segments are decoded as soon as they arrive, only the
+/// trailing incomplete segment is buffered. Tokens split across chunk boundaries,
+/// e.g. `"gz"` followed by `"ip, br"`, are handled transparently. Decoding yields
+/// the same result as `decode_header_value_with_mode` on the concatenated input.
+#[derive(Debug, Default)]
+pub struct AcceptEncodingParser {
+    mode: DecodeMode,
+    pending: String,
+    parsed: Vec<(Encoding, QualityValue)>,
+    error: Option<AcceptEncodingDecodeError>,
+}
+
+impl AcceptEncodingParser {
+    /// Creates a new parser using lenient decoding
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new parser using the given decode mode
+    pub fn with_mode(mode: DecodeMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Feeds the next fragment of the header value.
+    ///
+    /// Errors are deferred until `finish`; input after the first error is ignored.
+    pub fn feed(&mut self, chunk: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        self.pending.push_str(chunk);
+        let Some(last_comma) = self.pending.rfind(',') else {
+            return;
+        };
+        for part in self.pending[..last_comma].split(',') {
+            match decode_segment(part, self.mode, 1.0) {
+                Ok(entry) => self.parsed.push(entry),
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
+                }
+            }
+        }
+        self.pending.drain(..=last_comma);
+    }
+
+    /// Decodes the remaining input and returns all encodings with their quality values
+    pub fn finish(mut self) -> Result<Vec<(Encoding, QualityValue)>, AcceptEncodingDecodeError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.parsed.push(decode_segment(&self.pending, self.mode, 1.0)?);
+        Ok(self.parsed)
+    }
+}
+
+/// Returns true if `v` matches the RFC 9110 qvalue grammar:
+/// `( "0" [ "." 0*3DIGIT ] ) / ( "1" [ "." 0*3("0") ] )`
+fn is_qvalue(v: &str) -> bool {
+    let (int, frac) = v.split_once('.').unwrap_or((v, ""));
+    frac.len() <= 3
+        && match int {
+            "0" => frac.bytes().all(|b| b.is_ascii_digit()),
+            "1" => frac.bytes().all(|b| b == b'0'),
+            _ => false,
+        }
+}
+
+/// Replaces obsolete line folding, a line break followed by spaces or tabs, with a
+/// single space. Other CR and LF characters are left in place.
+fn unfold(part: &str) -> Cow<'_, str> {
+    if !part.contains(['\r', '\n']) {
+        return Cow::Borrowed(part);
+    }
+    let mut unfolded = String::with_capacity(part.len());
+    let mut rest = part;
+    while let Some(i) = rest.find(['\r', '\n']) {
+        let (before, line_break) = rest.split_at(i);
+        unfolded.push_str(before);
+        let after = line_break.strip_prefix("\r\n").or_else(|| line_break.strip_prefix('\n'));
+        match after {
+            Some(after) if after.starts_with([' ', '\t']) => {
+                unfolded.push(' ');
+                rest = after;
+            }
+            _ => {
+                unfolded.push_str(&line_break[..1]);
+                rest = &line_break[1..];
+            }
+        }
+    }
+    unfolded.push_str(rest);
+    Cow::Owned(unfolded)
+}
+
+/// Returns true for characters which must not appear anywhere in a header value:
+/// ASCII control characters other than HTAB, and the byte order mark.
+fn is_forbidden_char(c: char) -> bool {
+    (c.is_ascii_control() && c != '\t') || c == '\u{feff}'
+}
+
+/// A decoded Accept-Encoding header value that keeps the original text around
+///
+/// Optional whitespace is not semantically meaningful, but a proxy may want to
+/// forward the header byte-for-byte when it does not modify it.
+#[derive(Debug, Clone)]
+pub struct PreservedHeaderValue {
+    raw: String,
+    segments: Vec<String>,
+    encodings: Vec<(Encoding, QualityValue)>,
+    raw_qualities: Vec<Option<String>>,
+}
+
+impl PreservedHeaderValue {
+    /// Returns the original header value
+    #[inline]
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Returns the original comma-separated segments, including their whitespace
+    #[inline]
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// Returns the decoded encodings and their quality values
+    #[inline]
+    pub fn items(&self) -> &[(Encoding, QualityValue)] {
+        &self.encodings
+    }
+
+    /// Returns the quality value of each entry exactly as it was written, e.g.
+    /// `"0.3330"`, or `None` if the entry had no `q` parameter
+    #[inline]
+    pub fn raw_qualities(&self) -> &[Option<String>] {
+        &self.raw_qualities
+    }
+
+    /// Returns the header value to forward for `encodings`.
+    ///
+    /// If `encodings` is identical to the decoded entries the original bytes are
+    /// returned untouched, otherwise `encodings` is re-encoded in canonical form.
+    /// Entries unchanged from the original keep their raw quality value string if
+    /// it is a valid qvalue, e.g. `"0.50"`.
+    pub fn forward_value(
+        &self,
+        encodings: &[(Encoding, QualityValue)],
+    ) -> Result<Cow<'_, str>, AcceptEncodingEncodeError> {
+        if encodings == self.encodings.as_slice() {
+            return Ok(Cow::Borrowed(&self.raw));
+        }
+        if encodings.is_empty() {
+            return Err(AcceptEncodingEncodeError::EmptyEncodings);
+        }
+        validate_qualities(encodings)?;
+        let mut buf = String::new();
+        for (i, (enc, q)) in encodings.iter().enumerate() {
+            if i > 0 {
+                buf.push_str(", ");
+            }
+            buf.push_str(enc.as_str());
+            match self.raw_quality_of(enc, *q) {
+                Some(raw) => {
+                    let _ = write!(buf, ";q={raw}");
+                }
+                None => write_quality(&mut buf, *q),
+            }
+        }
+        Ok(Cow::Owned(buf))
+    }
+
+    /// Returns the raw quality value string of the original entry matching `enc`
+    /// and `q`, if any and it is a valid qvalue that parses to `q`
+    fn raw_quality_of(&self, enc: &Encoding, q: QualityValue) -> Option<&str> {
+        self.encodings
+            .iter()
+            .zip(&self.raw_qualities)
+            .find(|((e, eq), _)| e == enc && *eq == q)
+            .and_then(|(_, raw)| raw.as_deref())
+            .filter(|raw| is_qvalue(raw) && raw.parse::<QualityValue>().is_ok_and(|v| v == q))
+    }
+}
+
+/// Decodes Accept-Encoding header value like `decode_header_value`, preserving
+/// the original text and its raw segments
+pub fn decode_header_value_preserving(
+    value: &str,
+) -> Result<PreservedHeaderValue, AcceptEncodingDecodeError> {
+    let encodings = decode_header_value(value)?;
+    Ok(PreservedHeaderValue {
+        raw: value.to_string(),
+        segments: value.split(',').map(str::to_string).collect(),
+        raw_qualities: value.split(',').map(raw_quality).collect(),
+        encodings,
+    })
+}
+
+/// Returns the raw `q` parameter value of a segment, the last one wins like in
+/// lenient decoding
+fn raw_quality(segment: &str) -> Option<String> {
+    segment
+        .split(';')
+        .skip(1)
+        .filter_map(|p| p.trim().strip_prefix("q="))
+        .last()
+        .map(str::to_string)
+}
+
+/// Error type for Accept-Encoding header value encoding
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AcceptEncodingEncodeError {
+    #[error("encodings cannot be empty")]
+    EmptyEncodings,
+    #[error("invalid quality value: {0}, must be between 0 and 1")]
+    InvalidQuality(f32),
+    #[error("not a valid header value: {0}")]
+    InvalidHeaderValue(String),
+}
+
+/// Options for `encode_header_value_with_options`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Skips `Encoding::Identity` entries, as identity is implicitly acceptable.
+    /// Entries rejecting identity with `q=0` are always emitted.
+    pub omit_identity: bool,
+}
+
+/// Encodes a list of encodings with quality values into Accept-Encoding header value
+pub fn encode_header_value(
+    encodings: &[(Encoding, QualityValue)],
+) -> Result<String, AcceptEncodingEncodeError> {
+    let mut buf = String::new();
+    encode_header_value_into(encodings, &mut buf)?;
+    Ok(buf)
+}
+
+/// Appends the Accept-Encoding header value for `encodings` to `buf`, like
+/// `encode_header_value` but without allocating a new `String`.
+///
+/// On error `buf` is left unchanged.
+pub fn encode_header_value_into(
+    encodings: &[(Encoding, QualityValue)],
+    buf: &mut String,
+) -> Result<(), AcceptEncodingEncodeError> {
+    encode_into(encodings, EncodeOptions::default(), buf)
+}
+
+/// Encodes a list of encodings like `encode_header_value`, but in a canonical order:
+/// by quality value descending, then by token ascending, ignoring ASCII case.
+///
+/// Lists which differ only in order encode to the same string, e.g. for cache keys.
+pub fn encode_header_value_canonical(
+    encodings: &[(Encoding, QualityValue)],
+) -> Result<String, AcceptEncodingEncodeError> {
+    let mut sorted = encodings.to_vec();
+    sorted.sort_by(|(enc_a, q_a), (enc_b, q_b)| {
+        let token_a = enc_a.as_str().bytes().map(|b| b.to_ascii_lowercase());
+        let token_b = enc_b.as_str().bytes().map(|b| b.to_ascii_lowercase());
+        q_b.total_cmp(q_a).then_with(|| token_a.cmp(token_b))
+    });
+    encode_header_value(&sorted)
+}
+
+/// Encodes a list of encodings with quality values into Accept-Encoding header value
+/// using the given options.
+///
+/// Returns `AcceptEncodingEncodeError::EmptyEncodings` if no entry is left to encode.
+pub fn encode_header_value_with_options(
     encodings: &[(Encoding, QualityValue)],
+    options: EncodeOptions,
 ) -> Result<String, AcceptEncodingEncodeError> {
-    if encodings.is_empty() {
+    let mut buf = String::new();
+    encode_into(encodings, options, &mut buf)?;
+    Ok(buf)
+}
+
+/// Appends the encoded header value to `buf`, leaving it unchanged on error
+fn encode_into(
+    encodings: &[(Encoding, QualityValue)],
+    options: EncodeOptions,
+    buf: &mut String,
+) -> Result<(), AcceptEncodingEncodeError> {
+    validate_qualities(encodings)?;
+    let start = buf.len();
+    let emitted = encodings
+        .iter()
+        .filter(|(enc, q)| !(options.omit_identity && enc.is_identity() && *q > 0.0));
+    for (i, (enc, q)) in emitted.enumerate() {
+        if i > 0 {
+            buf.push_str(", ");
+        }
+        buf.push_str(enc.as_str());
+        write_quality(buf, *q);
+    }
+    if buf.len() == start {
         return Err(AcceptEncodingEncodeError::EmptyEncodings);
     }
+    Ok(())
+}
+
+/// Rejects quality values which cannot be written as a qvalue: NaN, infinities and
+/// anything outside of `0..=1`
+fn validate_qualities(
+    encodings: &[(Encoding, QualityValue)],
+) -> Result<(), AcceptEncodingEncodeError> {
+    match encodings.iter().find(|(_, q)| !(0.0..=1.0).contains(q)) {
+        Some((_, q)) => Err(AcceptEncodingEncodeError::InvalidQuality(*q)),
+        None => Ok(()),
+    }
+}
+
+/// Clamps `q` to `0..=1`, treating NaN as not acceptable
+fn clamp_quality(q: QualityValue) -> QualityValue {
+    if q.is_nan() { 0.0 } else { q.clamp(0.0, 1.0) }
+}
+
+/// Appends the `;q=` parameter for `q` in canonical form
+fn write_quality(buf: &mut String, q: QualityValue) {
+    // Only include q if not exactly 1.0
+    if (q - 1.0).abs() > QualityValue::EPSILON {
+        // format with up to 3 decimals, trim trailing zeros and dot in place
+        let _ = write!(buf, ";q={q:.3}");
+        while buf.ends_with('0') {
+            buf.pop();
+        }
+        if buf.ends_with('.') {
+            buf.pop();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "http_crates"))]
+mod http_crates_tests {
+    use super::*;
+    use headers::Header;
+
+    #[test]
+    fn test_basic_decode() {
+        let value = headers::HeaderValue::from_static("gzip, deflate, br");
+        let mut iter = std::iter::once(&value);
+        let enc = AcceptEncoding::decode(&mut iter).unwrap();
+
+        assert_eq!(enc.items().len(), 3);
+        assert!(matches!(enc.items()[0].0, Encoding::Gzip));
+        assert!(matches!(enc.items()[1].0, Encoding::Deflate));
+        assert!(matches!(enc.items()[2].0, Encoding::Br));
+        assert!((enc.items()[0].1 - 1.0).abs() < QualityValue::EPSILON);
+    }
+
+    #[test]
+    fn test_out_of_range_quality_round_trips() {
+        use headers::HeaderMapExt;
+
+        let value = headers::HeaderValue::from_static("gzip;q=2, br;q=-1");
+        let enc = AcceptEncoding::decode(&mut std::iter::once(&value)).unwrap();
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0), (Encoding::Br, 0.0)]);
+
+        let mut map = http::HeaderMap::new();
+        map.typed_insert(enc);
+        assert_eq!(map[http::header::ACCEPT_ENCODING], "gzip, br;q=0");
+    }
+
+    #[test]
+    fn test_quality_values() {
+        let value = headers::HeaderValue::from_static("gzip;q=1.0, deflate;q=0.5, br;q=0.1");
+        let mut iter = std::iter::once(&value);
+        let enc = AcceptEncoding::decode(&mut iter).unwrap();
+
+        assert_eq!(enc.items().len(), 3);
+        assert!(matches!(enc.items()[0].0, Encoding::Gzip));
+        assert!((enc.items()[0].1 - 1.0).abs() < QualityValue::EPSILON);
+        assert!(matches!(enc.items()[1].0, Encoding::Deflate));
+        assert!((enc.items()[1].1 - 0.5).abs() < QualityValue::EPSILON);
+        assert!(matches!(enc.items()[2].0, Encoding::Br));
+        assert!((enc.items()[2].1 - 0.1).abs() < QualityValue::EPSILON);
+    }
+
+    #[test]
+    fn test_encode() {
+        let encodings = vec![
+            (Encoding::Gzip, 1.0),
+            (Encoding::Deflate, 0.5),
+            (Encoding::Br, 0.1),
+        ];
+        let enc = AcceptEncoding::new(encodings).unwrap();
+        let mut values = Vec::new();
+        enc.encode(&mut values);
+
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].to_str().unwrap(), "gzip, deflate;q=0.5, br;q=0.1");
+    }
+
+    #[test]
+    fn test_empty() {
+        let encodings = vec![];
+        // constructing AcceptEncoding with empty should error
+        assert!(AcceptEncoding::new(encodings).is_err());
+        // and encode should not push anything when constructed with non-empty then cleared scenario isn't possible via API
+    }
+
+    #[test]
+    fn test_try_from_header_value() {
+        let value = headers::HeaderValue::from_static("gzip, br;q=0.5");
+        let enc = AcceptEncoding::try_from(&value).unwrap();
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0), (Encoding::Br, 0.5)]);
+
+        let value = headers::HeaderValue::from_static("gzip;q=high");
+        assert!(matches!(
+            AcceptEncoding::try_from(&value).err().unwrap(),
+            AcceptEncodingDecodeError::InvalidQualityValue(_)
+        ));
+
+        let value = headers::HeaderValue::from_bytes(b"gzip\xff").unwrap();
+        assert!(matches!(
+            AcceptEncoding::try_from(&value).err().unwrap(),
+            AcceptEncodingDecodeError::InvalidToken(_)
+        ));
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = http::HeaderMap::new();
+        headers.append(
+            http::header::ACCEPT_ENCODING,
+            headers::HeaderValue::from_static("gzip"),
+        );
+        headers.append(
+            http::header::ACCEPT_ENCODING,
+            headers::HeaderValue::from_static("br;q=0.5"),
+        );
+        let enc = AcceptEncoding::from_headers(&headers).unwrap();
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0), (Encoding::Br, 0.5)]);
+    }
+
+    #[test]
+    fn test_empty_header_is_identity_only() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            http::header::ACCEPT_ENCODING,
+            headers::HeaderValue::from_static(""),
+        );
+
+        let enc = AcceptEncoding::from_headers(&headers).unwrap();
+        assert_eq!(enc.items(), AcceptEncoding::identity_only().items());
+        assert_eq!(enc.negotiate(&[Encoding::Gzip]), NegotiationResult::Identity);
+
+        let value = headers::HeaderValue::from_static("  ");
+        let enc = AcceptEncoding::decode(&mut std::iter::once(&value)).unwrap();
+        assert_eq!(enc.items(), AcceptEncoding::identity_only().items());
+
+        let enc = AcceptEncoding::try_from(&value).unwrap();
+        assert_eq!(enc.items(), AcceptEncoding::identity_only().items());
+
+        // Blank lines next to non-blank ones are ignored
+        headers.append(
+            http::header::ACCEPT_ENCODING,
+            headers::HeaderValue::from_static("gzip"),
+        );
+        let enc = AcceptEncoding::from_headers(&headers).unwrap();
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0)]);
+    }
+
+    #[test]
+    fn test_insert_into() {
+        let mut headers = http::HeaderMap::new();
+        let enc = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0), (Encoding::Br, 0.5)]).unwrap();
+        enc.insert_into(&mut headers).unwrap();
+        assert_eq!(
+            headers.get(http::header::ACCEPT_ENCODING).unwrap(),
+            "gzip, br;q=0.5"
+        );
+
+        // Replaces all existing values
+        headers.append(
+            http::header::ACCEPT_ENCODING,
+            headers::HeaderValue::from_static("zstd"),
+        );
+        let enc = AcceptEncoding::new(vec![(Encoding::Deflate, 1.0)]).unwrap();
+        enc.insert_into(&mut headers).unwrap();
+        let values: Vec<_> = headers.get_all(http::header::ACCEPT_ENCODING).iter().collect();
+        assert_eq!(values, vec!["deflate"]);
+
+        let mut empty = enc.clone();
+        empty.truncate(0);
+        empty.insert_into(&mut headers).unwrap();
+        assert!(!headers.contains_key(http::header::ACCEPT_ENCODING));
+
+        // Errors leave the existing header in place
+        headers.insert(http::header::ACCEPT_ENCODING, headers::HeaderValue::from_static("br"));
+        let invalid = AcceptEncoding::new(vec![(Encoding::Custom("x\ny".to_string()), 1.0)]);
+        assert!(matches!(
+            invalid.unwrap().insert_into(&mut headers),
+            Err(AcceptEncodingEncodeError::InvalidHeaderValue(_))
+        ));
+        assert_eq!(headers[http::header::ACCEPT_ENCODING], "br");
+    }
+
+    #[test]
+    fn test_from_headers_rejections() {
+        let mut headers = http::HeaderMap::new();
+        let rejection = AcceptEncoding::from_headers(&headers).err().unwrap();
+        assert!(matches!(rejection, AcceptEncodingRejection::Missing));
+        assert_eq!(rejection.status(), http::StatusCode::BAD_REQUEST);
+
+        headers.insert(
+            http::header::ACCEPT_ENCODING,
+            headers::HeaderValue::from_static("gzip;q=abc"),
+        );
+        let rejection = AcceptEncoding::from_headers(&headers).err().unwrap();
+        assert!(matches!(rejection, AcceptEncodingRejection::Invalid(_)));
+        assert_eq!(rejection.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_sort_ascending() {
+        let mut enc = AcceptEncoding::new(vec![
+            (Encoding::Gzip, 1.0),
+            (Encoding::Deflate, 0.5),
+            (Encoding::Br, 0.1),
+        ])
+        .unwrap();
+        enc.sort_ascending();
+
+        assert_eq!(enc.items().len(), 3);
+        assert!(matches!(enc.items()[0].0, Encoding::Br));
+        assert!((enc.items()[0].1 - 0.1).abs() < QualityValue::EPSILON);
+        assert!(matches!(enc.items()[1].0, Encoding::Deflate));
+        assert!((enc.items()[1].1 - 0.5).abs() < QualityValue::EPSILON);
+        assert!(matches!(enc.items()[2].0, Encoding::Gzip));
+        assert!((enc.items()[2].1 - 1.0).abs() < QualityValue::EPSILON);
+    }
+
+    #[test]
+    fn test_sort_descending() {
+        let mut enc = AcceptEncoding::new(vec![
+            (Encoding::Br, 0.1),
+            (Encoding::Deflate, 0.5),
+            (Encoding::Gzip, 1.0),
+        ])
+        .unwrap();
+        enc.sort_descending();
+
+        assert_eq!(enc.items().len(), 3);
+        assert!(matches!(enc.items()[0].0, Encoding::Gzip));
+        assert!((enc.items()[0].1 - 1.0).abs() < QualityValue::EPSILON);
+        assert!(matches!(enc.items()[1].0, Encoding::Deflate));
+        assert!((enc.items()[1].1 - 0.5).abs() < QualityValue::EPSILON);
+        assert!(matches!(enc.items()[2].0, Encoding::Br));
+        assert!((enc.items()[2].1 - 0.1).abs() < QualityValue::EPSILON);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_header_value_parses_list_and_qualities() {
+        let parsed = decode_header_value("gzip, deflate;q=0.5, br;q=0.100").unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert!(matches!(parsed[0].0, Encoding::Gzip));
+        assert!((parsed[0].1 - 1.0).abs() < QualityValue::EPSILON);
+        assert!(matches!(parsed[1].0, Encoding::Deflate));
+        assert!((parsed[1].1 - 0.5).abs() < QualityValue::EPSILON);
+        assert!(matches!(parsed[2].0, Encoding::Br));
+        assert!((parsed[2].1 - 0.1).abs() < QualityValue::EPSILON);
+    }
+
+    #[test]
+    fn decode_header_value_handles_errors() {
+        // empty tuple
+        assert!(matches!(
+            decode_header_value(" , gzip"),
+            Err(AcceptEncodingDecodeError::EmptyEncodingWeightTuple)
+        ));
+        // empty name
+        assert!(matches!(
+            decode_header_value(";q=1.0"),
+            Err(AcceptEncodingDecodeError::EmptyEncodingName)
+        ));
+        // invalid q
+        assert!(matches!(
+            decode_header_value("gzip;q=abc"),
+            Err(AcceptEncodingDecodeError::InvalidQualityValue(_))
+        ));
+        // unexpected directive
+        assert!(matches!(
+            decode_header_value("gzip;foo=bar"),
+            Err(AcceptEncodingDecodeError::UnexpectedDirective(s)) if s=="foo=bar"
+        ));
+    }
+
+    #[test]
+    fn decode_header_value_preserving_keeps_raw_segments() {
+        let preserved = decode_header_value_preserving("gzip ,  br;q=0.5,deflate").unwrap();
+        assert_eq!(preserved.raw(), "gzip ,  br;q=0.5,deflate");
+        assert_eq!(preserved.segments(), &["gzip ", "  br;q=0.5", "deflate"]);
+        assert_eq!(
+            preserved.items(),
+            &[
+                (Encoding::Gzip, 1.0),
+                (Encoding::Br, 0.5),
+                (Encoding::Deflate, 1.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_header_value_preserving_forwards_original_when_unmodified() {
+        let preserved = decode_header_value_preserving("gzip ,  br;q=0.5").unwrap();
+
+        let unmodified = preserved.items().to_vec();
+        let forwarded = preserved.forward_value(&unmodified).unwrap();
+        assert!(matches!(forwarded, Cow::Borrowed("gzip ,  br;q=0.5")));
+
+        let modified = vec![(Encoding::Gzip, 1.0)];
+        let forwarded = preserved.forward_value(&modified).unwrap();
+        assert!(matches!(forwarded, Cow::Owned(ref s) if s == "gzip"));
+    }
+
+    #[test]
+    fn decode_header_value_preserving_keeps_raw_qualities() {
+        let preserved = decode_header_value_preserving("gzip;q=0.3330, br").unwrap();
+        assert_eq!(
+            preserved.raw_qualities(),
+            &[Some("0.3330".to_string()), None]
+        );
+        assert_eq!(preserved.items()[0], (Encoding::Gzip, 0.333));
+
+        let unmodified = preserved.items().to_vec();
+        let forwarded = preserved.forward_value(&unmodified).unwrap();
+        assert_eq!(forwarded, "gzip;q=0.3330, br");
+
+        // More than three decimals is not a valid qvalue, so it is not reused
+        let forwarded = preserved.forward_value(&[(Encoding::Gzip, 0.333)]).unwrap();
+        assert_eq!(forwarded, "gzip;q=0.333");
+
+        let preserved = decode_header_value_preserving("gzip;q=0.500, br").unwrap();
+        let forwarded = preserved.forward_value(&[(Encoding::Gzip, 0.5)]).unwrap();
+        assert_eq!(forwarded, "gzip;q=0.500");
+
+        let forwarded = preserved.forward_value(&[(Encoding::Gzip, 0.25)]).unwrap();
+        assert_eq!(forwarded, "gzip;q=0.25");
+    }
+
+    #[test]
+    fn decode_header_value_preserving_does_not_reuse_invalid_raw_qualities() {
+        let preserved = decode_header_value_preserving("gzip;q=2, br;q=0.5").unwrap();
+        assert_eq!(preserved.items()[0], (Encoding::Gzip, 1.0));
+        let forwarded = preserved.forward_value(&[(Encoding::Gzip, 1.0)]).unwrap();
+        assert_eq!(forwarded, "gzip");
+
+        let preserved = decode_header_value_preserving("gzip;q=1e-1, br").unwrap();
+        assert_eq!(preserved.items()[0], (Encoding::Gzip, 0.1));
+        let forwarded = preserved.forward_value(&[(Encoding::Gzip, 0.1)]).unwrap();
+        assert_eq!(forwarded, "gzip;q=0.1");
+    }
+
+    #[test]
+    fn debug_includes_sort_state() {
+        let mut accept =
+            AcceptEncoding::new(vec![(Encoding::Gzip, 0.5), (Encoding::Br, 1.0)]).unwrap();
+        assert!(format!("{accept:?}").contains("sort: Unsorted"));
+        accept.sort_descending();
+        assert_eq!(
+            format!("{accept:?}"),
+            "AcceptEncoding { encodings: [(Br, 1.0), (Gzip, 0.5)], sort: Descending }"
+        );
+    }
+
+    #[test]
+    fn to_minimal_header_value_drops_redundant_entries() {
+        let cases = [
+            ("gzip;q=1.0, br;q=0.500", "gzip, br;q=0.5"),
+            ("gzip, br;q=0, deflate;q=0", "gzip"),
+            ("gzip, identity;q=0", "gzip, identity;q=0"),
+            ("gzip;q=0.5, br, *;q=0.5", "br, *;q=0.5"),
+            ("gzip, identity;q=0.5, *;q=0", "gzip, identity;q=0.5"),
+            ("gzip, *;q=0", "gzip, *;q=0"),
+            ("gzip;q=0.2, br, gzip;q=0.8", "br, gzip;q=0.8"),
+            ("gzip, *;q=0, br;q=0", "gzip, *;q=0"),
+            ("gzip;q=0", "gzip;q=0"),
+            ("br;q=0, deflate;q=0", "deflate;q=0"),
+        ];
+        for (verbose, minimal) in cases {
+            let accept = AcceptEncoding::parse(verbose).unwrap();
+            let encoded = accept.to_minimal_header_value().unwrap();
+            assert_eq!(encoded, minimal, "{verbose:?}");
+
+            let decoded = AcceptEncoding::parse(&encoded).unwrap();
+            let probes = accept.items().iter().map(|(enc, _)| enc.clone()).chain([
+                Encoding::Identity,
+                Encoding::Zstd,
+                Encoding::Custom("x-unlisted".to_string()),
+            ]);
+            let server = [Encoding::Gzip, Encoding::Br, Encoding::Deflate, Encoding::Zstd];
+            let acceptable = |accept: &AcceptEncoding, enc: &Encoding| {
+                negotiation::effective_quality(accept, enc).is_some_and(|q| q > 0.0)
+            };
+            for enc in probes {
+                assert_eq!(
+                    acceptable(&accept, &enc),
+                    acceptable(&decoded, &enc),
+                    "{verbose:?} differs for {enc}"
+                );
+            }
+            assert_eq!(
+                negotiation::identity_acceptable(&accept),
+                negotiation::identity_acceptable(&decoded)
+            );
+            for n in 1..=server.len() {
+                assert_eq!(
+                    accept.negotiate(&server[..n]),
+                    decoded.negotiate(&server[..n]),
+                    "{verbose:?} negotiates differently"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn advertise_emits_server_header() {
+        let advertised = AcceptEncoding::advertise(&[Encoding::Gzip, Encoding::Br]);
+        assert_eq!(advertised.items(), &[(Encoding::Gzip, 1.0), (Encoding::Br, 1.0)]);
+        assert_eq!(advertised.to_advertised_header_value().unwrap(), "gzip, br");
+        assert_eq!(AcceptEncoding::advertise(&[]).to_advertised_header_value().unwrap(), "");
+    }
+
+    #[test]
+    fn iter_descending_honors_sort_state() {
+        let mut accept = AcceptEncoding::parse("gzip;q=0.5, br, *;q=0, deflate;q=0.8").unwrap();
+        let expected = [
+            (&Encoding::Br, 1.0),
+            (&Encoding::Deflate, 0.8),
+            (&Encoding::Gzip, 0.5),
+            (&Encoding::Wildcard, 0.0),
+        ];
+        assert_eq!(accept.iter_descending().collect::<Vec<_>>(), expected);
+        assert_eq!(accept.iter_descending().len(), 4);
+
+        accept.sort_ascending();
+        assert_eq!(accept.iter_descending().collect::<Vec<_>>(), expected);
+
+        accept.sort_descending();
+        assert_eq!(accept.iter_descending().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn to_header_value_sorted_emits_best_first() {
+        let accept = AcceptEncoding::new(vec![
+            (Encoding::Deflate, 0.5),
+            (Encoding::Br, 1.0),
+            (Encoding::Gzip, 0.8),
+        ])
+        .unwrap();
+        assert_eq!(accept.to_header_value().unwrap(), "deflate;q=0.5, br, gzip;q=0.8");
+        assert_eq!(
+            accept.to_header_value_sorted().unwrap(),
+            "br, gzip;q=0.8, deflate;q=0.5"
+        );
+        assert_eq!(accept.items()[0].0, Encoding::Deflate);
+    }
+
+    #[test]
+    fn decode_header_value_into_reuses_buffer() {
+        let mut out = Vec::new();
+        decode_header_value_into("gzip, deflate, br, zstd", &mut out).unwrap();
+        assert_eq!(out.len(), 4);
+        let capacity = out.capacity();
+
+        decode_header_value_into("br;q=0.5", &mut out).unwrap();
+        assert_eq!(out, vec![(Encoding::Br, 0.5)]);
+        assert_eq!(out.capacity(), capacity);
+
+        assert!(decode_header_value_into("gzip, ;q=1", &mut out).is_err());
+        assert_eq!(out, vec![(Encoding::Gzip, 1.0)]);
+    }
+
+    #[test]
+    fn decode_header_value_obsolete_line_folding() {
+        for value in ["gzip,\r\n deflate", "gzip,\n\tdeflate", "gzip\r\n  , deflate"] {
+            assert_eq!(
+                decode_header_value(value).unwrap(),
+                vec![(Encoding::Gzip, 1.0), (Encoding::Deflate, 1.0)],
+                "{value:?}"
+            );
+        }
+        assert_eq!(
+            decode_header_value_with_mode("gzip;\r\n q=0.5", DecodeMode::Strict).unwrap(),
+            vec![(Encoding::Gzip, 0.5)]
+        );
+        assert_eq!(
+            decode_header_value_relaxed("gzip,\r\n br"),
+            vec![(Encoding::Gzip, 1.0), (Encoding::Br, 1.0)]
+        );
+
+        // Line breaks which are not followed by whitespace stay invalid
+        for value in ["gz\r\nip", "gzip\r\n, br", "gzip,\r deflate", "gzip,\r\n"] {
+            assert!(
+                matches!(
+                    decode_header_value(value),
+                    Err(AcceptEncodingDecodeError::InvalidToken(_))
+                ),
+                "{value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn decode_header_value_quoted_token() {
+        assert!(matches!(
+            decode_header_value_with_mode("\"gzip\"", DecodeMode::Strict),
+            Err(AcceptEncodingDecodeError::InvalidToken(s)) if s == "\"gzip\""
+        ));
+
+        let parsed = decode_header_value_with_mode("\"gzip\"", DecodeMode::Lenient).unwrap();
+        assert!(matches!(&parsed[0].0, Encoding::Custom(s) if s == "\"gzip\""));
+    }
+
+    #[test]
+    fn decode_header_value_empty_parameter() {
+        assert!(matches!(
+            decode_header_value_with_mode("gzip;;q=0.5", DecodeMode::Strict),
+            Err(AcceptEncodingDecodeError::EmptyParameter(s)) if s == "gzip"
+        ));
+
+        let parsed = decode_header_value_with_mode("gzip;;q=0.5", DecodeMode::Lenient).unwrap();
+        assert_eq!(parsed, vec![(Encoding::Gzip, 0.5)]);
+    }
+
+    #[test]
+    fn decode_header_value_duplicate_quality() {
+        assert!(matches!(
+            decode_header_value_with_mode("gzip;q=0.5;q=0.8", DecodeMode::Strict),
+            Err(AcceptEncodingDecodeError::DuplicateQuality(s)) if s == "gzip"
+        ));
+
+        let parsed =
+            decode_header_value_with_mode("gzip;q=0.5;q=0.8", DecodeMode::Lenient).unwrap();
+        assert_eq!(parsed, vec![(Encoding::Gzip, 0.8)]);
+    }
+
+    #[test]
+    fn decode_header_value_strict_qvalue() {
+        for q in ["1e-1", "0x1", "inf", "NaN", "1.001", "0.1234", "-0", "+1", ".5", "2"] {
+            let value = format!("gzip;q={q}");
+            assert!(
+                matches!(
+                    decode_header_value_with_mode(&value, DecodeMode::Strict),
+                    Err(AcceptEncodingDecodeError::InvalidQualityValue(s)) if s == q
+                ),
+                "{q} should be rejected"
+            );
+        }
+
+        let valid = [
+            ("0", 0.0),
+            ("0.", 0.0),
+            ("0.125", 0.125),
+            ("1", 1.0),
+            ("1.000", 1.0),
+        ];
+        for (q, expected) in valid {
+            let value = format!("gzip;q={q}");
+            let parsed = decode_header_value_with_mode(&value, DecodeMode::Strict).unwrap();
+            assert_eq!(parsed[0].1, expected);
+        }
+
+        // Lenient mode keeps accepting anything f32 can parse
+        let parsed = decode_header_value_with_mode("gzip;q=1e-1", DecodeMode::Lenient).unwrap();
+        assert_eq!(parsed[0].1, 0.1);
+    }
+
+    #[test]
+    fn decode_header_value_rejects_control_characters() {
+        for mode in [DecodeMode::Lenient, DecodeMode::Strict] {
+            assert!(matches!(
+                decode_header_value_with_mode("gzip, b\x00r", mode),
+                Err(AcceptEncodingDecodeError::InvalidToken(s)) if s == " b\x00r"
+            ));
+            assert!(matches!(
+                decode_header_value_with_mode("\u{feff}gzip", mode),
+                Err(AcceptEncodingDecodeError::InvalidToken(_))
+            ));
+            assert!(matches!(
+                decode_header_value_with_mode("gzip;q=0.5\x7f", mode),
+                Err(AcceptEncodingDecodeError::InvalidToken(_))
+            ));
+        }
+        // HTAB is allowed as whitespace
+        assert!(decode_header_value("gzip,\tbr").is_ok());
+    }
+
+    #[test]
+    fn accept_encoding_parser_matches_decode_header_value() {
+        let value = "gzip, deflate;q=0.5, br;q=0.100, x-custom";
+        let expected = decode_header_value(value).unwrap();
+
+        let fragmentations: [&[&str]; 4] = [
+            &[value],
+            &["gz", "ip, deflate;q", "=0.5, br;q=0.100, x-cus", "tom"],
+            &["gzip,", " deflate;q=0.5,", " br;q=0.100,", " x-custom"],
+            &["", "gzip", ", deflate;q=0.5, br;q=0.100, x-custom", ""],
+        ];
+        for fragments in fragmentations {
+            let mut parser = AcceptEncodingParser::new();
+            for fragment in fragments.iter() {
+                parser.feed(fragment);
+            }
+            assert_eq!(parser.finish().unwrap(), expected);
+        }
+
+        // Every possible split point of the value
+        for i in 0..=value.len() {
+            let mut parser = AcceptEncodingParser::new();
+            parser.feed(&value[..i]);
+            parser.feed(&value[i..]);
+            assert_eq!(parser.finish().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn accept_encoding_parser_split_token() {
+        let mut parser = AcceptEncodingParser::new();
+        parser.feed("gz");
+        parser.feed("ip, br");
+        assert_eq!(
+            parser.finish().unwrap(),
+            vec![(Encoding::Gzip, 1.0), (Encoding::Br, 1.0)]
+        );
+    }
+
+    #[test]
+    fn accept_encoding_parser_errors() {
+        let mut parser = AcceptEncodingParser::new();
+        parser.feed("gzip;q=ab");
+        parser.feed("c, br");
+        assert!(matches!(
+            parser.finish(),
+            Err(AcceptEncodingDecodeError::InvalidQualityValue(v)) if v == "abc"
+        ));
+
+        let mut parser = AcceptEncodingParser::with_mode(DecodeMode::Strict);
+        parser.feed("\"gzip\"");
+        assert!(matches!(
+            parser.finish(),
+            Err(AcceptEncodingDecodeError::InvalidToken(_))
+        ));
+
+        assert!(matches!(
+            AcceptEncodingParser::new().finish(),
+            Err(AcceptEncodingDecodeError::EmptyEncodingWeightTuple)
+        ));
+    }
+
+    #[test]
+    fn encode_header_value_formats_properly() {
+        let value = encode_header_value(&[
+            (Encoding::Gzip, 1.0),
+            (Encoding::Deflate, 0.5),
+            (Encoding::Br, 0.1),
+        ])
+        .unwrap();
+        assert_eq!(value, "gzip, deflate;q=0.5, br;q=0.1");
+    }
+
+    #[test]
+    fn encode_header_value_omits_q_for_one_and_trims_trailing_zeros() {
+        let value = encode_header_value(&[
+            (Encoding::Gzip, 1.0),
+            (Encoding::Deflate, 0.5000),
+            (Encoding::Br, 0.1000),
+        ])
+        .unwrap();
+        // ensures trimming and omission of q=1
+        assert_eq!(value, "gzip, deflate;q=0.5, br;q=0.1");
+    }
+
+    #[test]
+    fn encode_header_value_errors_on_empty() {
+        assert!(matches!(
+            encode_header_value(&[]),
+            Err(AcceptEncodingEncodeError::EmptyEncodings)
+        ));
+    }
+
+    #[test]
+    fn encode_header_value_into_appends() {
+        let mut buf = String::from("Accept-Encoding: ");
+        encode_header_value_into(&[(Encoding::Gzip, 1.0), (Encoding::Br, 0.5)], &mut buf)
+            .unwrap();
+        assert_eq!(buf, "Accept-Encoding: gzip, br;q=0.5");
+
+        buf.push_str(", ");
+        encode_header_value_into(&[(Encoding::Identity, 0.0)], &mut buf).unwrap();
+        assert_eq!(buf, "Accept-Encoding: gzip, br;q=0.5, identity;q=0");
+
+        let mut buf = String::from("unchanged");
+        assert!(encode_header_value_into(&[], &mut buf).is_err());
+        assert!(encode_header_value_into(&[(Encoding::Gzip, 2.0)], &mut buf).is_err());
+        assert_eq!(buf, "unchanged");
+    }
+
+    #[test]
+    fn encode_header_value_canonical_ignores_order() {
+        let a = [
+            (Encoding::Gzip, 1.0),
+            (Encoding::Br, 1.0),
+            (Encoding::Identity, 0.5),
+        ];
+        let b = [
+            (Encoding::Identity, 0.5),
+            (Encoding::Br, 1.0),
+            (Encoding::Gzip, 1.0),
+        ];
+        assert_ne!(encode_header_value(&a).unwrap(), encode_header_value(&b).unwrap());
+        assert_eq!(
+            encode_header_value_canonical(&a).unwrap(),
+            "br, gzip, identity;q=0.5"
+        );
+        assert_eq!(
+            encode_header_value_canonical(&a).unwrap(),
+            encode_header_value_canonical(&b).unwrap()
+        );
+    }
+
+    #[test]
+    fn encode_header_value_rejects_invalid_quality() {
+        for q in [f32::NAN, f32::INFINITY, -0.5, 2.0] {
+            let result = encode_header_value(&[(Encoding::Gzip, 1.0), (Encoding::Br, q)]);
+            let Err(AcceptEncodingEncodeError::InvalidQuality(v)) = result else {
+                panic!("{q} should be rejected, got {result:?}");
+            };
+            assert_eq!(v.to_bits(), q.to_bits());
+        }
+        assert_eq!(encode_header_value(&[(Encoding::Gzip, 0.0)]).unwrap(), "gzip;q=0");
+    }
+
+    #[test]
+    fn decode_header_value_relaxed_tolerates_broken_input() {
+        assert_eq!(
+            decode_header_value_relaxed(",, gzip ,,br ;  Q = .5,"),
+            vec![(Encoding::Gzip, 1.0), (Encoding::Br, 0.5)]
+        );
+        assert_eq!(
+            decode_header_value_relaxed("gzip;q=2, br;q=-1, zstd;q=\"0.3\""),
+            vec![(Encoding::Gzip, 1.0), (Encoding::Br, 0.0), (Encoding::Zstd, 0.3)]
+        );
+        assert_eq!(
+            decode_header_value_relaxed("gzip;level=9;q=0.8, ;q=0.5, br;q=NaN, zstd;q=abc"),
+            vec![(Encoding::Gzip, 0.8)]
+        );
+        assert_eq!(
+            decode_header_value_relaxed("b\x00r, deflate"),
+            vec![(Encoding::Deflate, 1.0)]
+        );
+        assert!(decode_header_value_relaxed("").is_empty());
+        assert!(decode_header_value_relaxed(" , ,").is_empty());
+    }
+
+    #[test]
+    fn decode_header_value_with_default_q_applies_to_missing_q() {
+        let value = decode_header_value_with_default_q("gzip, br;q=0.5", 0.8).unwrap();
+        assert_eq!(value, vec![(Encoding::Gzip, 0.8), (Encoding::Br, 0.5)]);
+    }
+
+    #[test]
+    fn encode_header_value_omit_identity() {
+        let options = EncodeOptions {
+            omit_identity: true,
+        };
+
+        let value = encode_header_value_with_options(
+            &[(Encoding::Gzip, 1.0), (Encoding::Identity, 1.0)],
+            options,
+        )
+        .unwrap();
+        assert_eq!(value, "gzip");
+
+        let value = encode_header_value_with_options(
+            &[(Encoding::Gzip, 1.0), (Encoding::Identity, 0.0)],
+            options,
+        )
+        .unwrap();
+        assert_eq!(value, "gzip, identity;q=0");
+
+        assert!(matches!(
+            encode_header_value_with_options(&[(Encoding::Identity, 1.0)], options),
+            Err(AcceptEncodingEncodeError::EmptyEncodings)
+        ));
+    }
+
+    #[test]
+    fn test_as_map_collapses_duplicates_to_max() {
+        let enc = AcceptEncoding::new(vec![
+            (Encoding::Gzip, 0.5),
+            (Encoding::Br, 0.8),
+            (Encoding::Gzip, 0.9),
+            (Encoding::Gzip, 0.1),
+            (Encoding::Wildcard, 0.0),
+        ])
+        .unwrap();
+
+        let map = enc.as_map();
+        assert_eq!(map.len(), 3);
+        assert!((map[&Encoding::Gzip] - 0.9).abs() < QualityValue::EPSILON);
+        assert!((map[&Encoding::Br] - 0.8).abs() < QualityValue::EPSILON);
+        assert!((map[&Encoding::Wildcard] - 0.0).abs() < QualityValue::EPSILON);
+    }
+
+    #[test]
+    fn test_parse_empty_is_identity_only() {
+        for value in ["", "  ", "\t"] {
+            let enc = AcceptEncoding::parse(value).unwrap();
+            assert_eq!(
+                enc.items(),
+                &[(Encoding::Identity, 1.0), (Encoding::Wildcard, 0.0)]
+            );
+            assert!(enc.rejects(&Encoding::Gzip));
+            assert!(!enc.rejects(&Encoding::Identity));
+        }
+
+        // The raw decoder keeps rejecting empty values
+        assert!(decode_header_value("").is_err());
+        assert_eq!(
+            AcceptEncoding::parse("gzip").unwrap().items(),
+            &[(Encoding::Gzip, 1.0)]
+        );
+    }
+
+    #[test]
+    fn test_effective_preferred_matrix() {
+        use NegotiationResult::{Identity, NotAcceptable, Selected};
+
+        let gzip_br = [Encoding::Gzip, Encoding::Br];
+        let cases: [(&str, &[Encoding], NegotiationResult); 12] = [
+            // Plain preference and tie-breaking
+            ("gzip, br;q=0.5", &gzip_br, Selected(Encoding::Gzip)),
+            ("gzip, br", &gzip_br, Selected(Encoding::Br)),
+            // Wildcard on the client side
+            ("*", &gzip_br, Selected(Encoding::Br)),
+            ("gzip;q=0.2, *;q=0.5", &gzip_br, Selected(Encoding::Br)),
+            // Wildcard on the server side
+            ("zstd;q=0.5, gzip;q=0.2", &[Encoding::Wildcard], Selected(Encoding::Zstd)),
+            // Identity forbidden
+            ("gzip, identity;q=0", &[Encoding::Br], NotAcceptable),
+            ("gzip, *;q=0", &[Encoding::Br], NotAcceptable),
+            ("identity;q=1, *;q=0", &gzip_br, Identity),
+            // All q=0
+            ("gzip;q=0, br;q=0", &gzip_br, Identity),
+            ("gzip;q=0, br;q=0, identity;q=0", &gzip_br, NotAcceptable),
+            // No match
+            ("zstd", &gzip_br, Identity),
+            ("", &gzip_br, Identity),
+        ];
+        for (header, server, expected) in cases {
+            let accept = AcceptEncoding::parse(header).unwrap();
+            assert_eq!(accept.effective_preferred(server), expected, "{header:?}");
+            assert_eq!(
+                accept.to_sorted_descending().effective_preferred(server),
+                expected,
+                "{header:?} sorted"
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let warnings = |value| AcceptEncoding::validate(value).1;
+
+        let (enc, found) = AcceptEncoding::validate("gzip, br;q=0.5, *;q=0");
+        assert_eq!(enc.items().len(), 3);
+        assert!(found.is_empty());
+
+        let (enc, found) = AcceptEncoding::validate("gzip;q=1.5, br;q=0.3333");
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0), (Encoding::Br, 0.3333)]);
+        assert_eq!(
+            found,
+            [
+                ComplianceWarning::QualityOutOfRange("1.5".to_string()),
+                ComplianceWarning::TooManyDecimals("0.3333".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            warnings("gzip;level=9"),
+            [ComplianceWarning::UnknownParameter("level=9".to_string())]
+        );
+        assert_eq!(
+            warnings("gzip, br, gzip;q=0.5"),
+            [ComplianceWarning::DuplicateEncoding(Encoding::Gzip)]
+        );
+        assert_eq!(
+            warnings("gzip;q=0.5;q=0.7"),
+            [ComplianceWarning::DuplicateQuality("gzip".to_string())]
+        );
+        assert_eq!(
+            warnings("gzip,, br;"),
+            [
+                ComplianceWarning::EmptyElement,
+                ComplianceWarning::EmptyParameter("br".to_string()),
+            ]
+        );
+        assert_eq!(
+            warnings("gzip;Q=.5"),
+            [ComplianceWarning::NonStandardQuality("Q=.5".to_string())]
+        );
+
+        assert_eq!(
+            warnings("gzip,\r\n br"),
+            [ComplianceWarning::ObsoleteLineFolding]
+        );
+
+        let (enc, found) = AcceptEncoding::validate("gzip;q=abc, br");
+        assert_eq!(enc.items(), &[(Encoding::Br, 1.0)]);
+        assert_eq!(found, [ComplianceWarning::UnparsableQuality("abc".to_string())]);
+
+        let (enc, found) = AcceptEncoding::validate("  ");
+        assert_eq!(enc.items(), AcceptEncoding::identity_only().items());
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_entries_are_idempotent() {
+        let deduped = vec![(Encoding::Gzip, 1.0), (Encoding::Br, 1.0), (Encoding::Zstd, 0.5)];
+        let duplicated = vec![
+            (Encoding::Gzip, 1.0),
+            (Encoding::Br, 1.0),
+            (Encoding::Gzip, 1.0),
+            (Encoding::Zstd, 0.5),
+            (Encoding::Gzip, 1.0),
+        ];
+        let server = [Encoding::Gzip, Encoding::Br, Encoding::Zstd];
+        let weighted = [(Encoding::Gzip, 1.0), (Encoding::Br, 0.8), (Encoding::Zstd, 1.0)];
+        let wildcard = [(Encoding::Wildcard, 1.0)];
+
+        for sort in [SortState::Unsorted, SortState::Ascending, SortState::Descending] {
+            let [a, b] = [&deduped, &duplicated].map(|encodings| {
+                let mut accept = AcceptEncoding::new(encodings.clone()).unwrap();
+                match sort {
+                    SortState::Unsorted => {}
+                    SortState::Ascending => _ = accept.sort_ascending(),
+                    SortState::Descending => _ = accept.sort_descending(),
+                }
+                accept
+            });
+            let weighted_iter = || weighted.iter().map(|(e, q)| (e, *q));
+
+            assert_eq!(a.preferred(), b.preferred(), "{sort:?}");
+            assert_eq!(
+                a.preferred_allowed(server.iter()),
+                b.preferred_allowed(server.iter()),
+                "{sort:?}"
+            );
+            assert_eq!(
+                a.preferred_allowed_with_quality(server.iter()),
+                b.preferred_allowed_with_quality(server.iter()),
+                "{sort:?}"
+            );
+            assert_eq!(
+                a.preferred_allowed_weighted(weighted_iter()),
+                b.preferred_allowed_weighted(weighted_iter()),
+                "{sort:?}"
+            );
+            assert_eq!(a.negotiate(&server), b.negotiate(&server), "{sort:?}");
+            assert_eq!(a.negotiate_weighted(&weighted), b.negotiate_weighted(&weighted));
+            assert_eq!(a.scores(&weighted), b.scores(&weighted), "{sort:?}");
+            assert_eq!(a.scores(&wildcard), b.scores(&wildcard), "{sort:?}");
+            assert_eq!(
+                a.preferred_allowed_biased(&weighted, &Encoding::Zstd, 0.5),
+                b.preferred_allowed_biased(&weighted, &Encoding::Zstd, 0.5),
+                "{sort:?}"
+            );
+            assert_eq!(
+                a.preference_list(&server).collect::<Vec<_>>(),
+                b.preference_list(&server).collect::<Vec<_>>(),
+                "{sort:?}"
+            );
+            assert_eq!(a.best_or_identity(&server), b.best_or_identity(&server));
+        }
+    }
 
-    let mut buf = String::new();
-    for (i, (enc, q)) in encodings.iter().enumerate() {
-        if i > 0 {
-            buf.push_str(", ");
-        }
-        buf.push_str(&enc.to_string());
-        // Only include q if not exactly 1.0
-        if (*q - 1.0).abs() > QualityValue::EPSILON {
-            // format with up to 3 decimals, trim trailing zeros and dot
-            let mut qstr = format!("{q:.3}");
-            while qstr.ends_with('0') {
-                qstr.pop();
-            }
-            if qstr.ends_with('.') {
-                qstr.pop();
-            }
-            let _ = write!(buf, ";q={}", qstr);
-        }
+    #[test]
+    fn test_only_legacy() {
+        let modern = [Encoding::Gzip, Encoding::Br, Encoding::Zstd];
+        let only_legacy = |value| AcceptEncoding::parse(value).unwrap().only_legacy(&modern);
+
+        assert!(only_legacy("compress"));
+        assert!(only_legacy("x-compress, gzip;q=0"));
+        assert!(!only_legacy("compress, gzip;q=0.1"));
+        assert!(!only_legacy("compress, identity"));
+        assert!(!only_legacy("compress, *;q=0.1"));
+        assert!(!only_legacy("compress;q=0"));
     }
-    Ok(buf)
-}
 
-#[cfg(all(test, feature = "http_crates"))]
-mod http_crates_tests {
-    use super::*;
-    use headers::Header;
+    #[test]
+    fn test_prefers() {
+        let accept = AcceptEncoding::parse("gzip;q=0.8, br, deflate;q=0").unwrap();
+        assert_eq!(accept.prefers(&Encoding::Br, &Encoding::Gzip), Ordering::Greater);
+        assert_eq!(accept.prefers(&Encoding::Gzip, &Encoding::Br), Ordering::Less);
+        assert_eq!(accept.prefers(&Encoding::Br, &Encoding::Br), Ordering::Equal);
+        // Absent ranks below rejected
+        assert_eq!(accept.prefers(&Encoding::Zstd, &Encoding::Deflate), Ordering::Less);
+        assert_eq!(accept.prefers(&Encoding::Zstd, &Encoding::Lz4), Ordering::Equal);
+
+        let accept = AcceptEncoding::parse("gzip;q=0.8, *;q=0.5").unwrap();
+        assert_eq!(accept.prefers(&Encoding::Gzip, &Encoding::Zstd), Ordering::Greater);
+        let accept = AcceptEncoding::parse("gzip;q=0.3, *;q=0.5").unwrap();
+        assert_eq!(accept.prefers(&Encoding::Gzip, &Encoding::Zstd), Ordering::Less);
+    }
 
     #[test]
-    fn test_basic_decode() {
-        let value = headers::HeaderValue::from_static("gzip, deflate, br");
-        let mut iter = std::iter::once(&value);
-        let enc = AcceptEncoding::decode(&mut iter).unwrap();
+    fn test_parse_multi() {
+        let enc = AcceptEncoding::parse_multi(["gzip", "br;q=0.5"]).unwrap();
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0), (Encoding::Br, 0.5)]);
 
-        assert_eq!(enc.items().len(), 3);
-        assert!(matches!(enc.items()[0].0, Encoding::Gzip));
-        assert!(matches!(enc.items()[1].0, Encoding::Deflate));
-        assert!(matches!(enc.items()[2].0, Encoding::Br));
-        assert!((enc.items()[0].1 - 1.0).abs() < QualityValue::EPSILON);
+        let enc = AcceptEncoding::parse_multi([" ", "gzip"]).unwrap();
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0)]);
+
+        let enc = AcceptEncoding::parse_multi([" "]).unwrap();
+        assert_eq!(enc.items(), AcceptEncoding::identity_only().items());
+
+        assert!(AcceptEncoding::parse_multi([]).unwrap().items().is_empty());
+        assert!(AcceptEncoding::parse_multi(["gzip", "br;x=1"]).is_err());
     }
 
     #[test]
-    fn test_quality_values() {
-        let value = headers::HeaderValue::from_static("gzip;q=1.0, deflate;q=0.5, br;q=0.1");
-        let mut iter = std::iter::once(&value);
-        let enc = AcceptEncoding::decode(&mut iter).unwrap();
+    fn test_parse_whitespace_only_is_identity_only() {
+        let enc = AcceptEncoding::parse("   ").unwrap();
+        assert_eq!(enc.items(), AcceptEncoding::identity_only().items());
+        assert_eq!(enc.negotiate(&[Encoding::Gzip]), NegotiationResult::Identity);
 
-        assert_eq!(enc.items().len(), 3);
-        assert!(matches!(enc.items()[0].0, Encoding::Gzip));
-        assert!((enc.items()[0].1 - 1.0).abs() < QualityValue::EPSILON);
-        assert!(matches!(enc.items()[1].0, Encoding::Deflate));
-        assert!((enc.items()[1].1 - 0.5).abs() < QualityValue::EPSILON);
-        assert!(matches!(enc.items()[2].0, Encoding::Br));
-        assert!((enc.items()[2].1 - 0.1).abs() < QualityValue::EPSILON);
+        assert!(matches!(
+            decode_header_value("   "),
+            Err(AcceptEncodingDecodeError::EmptyEncodingWeightTuple)
+        ));
     }
 
     #[test]
-    fn test_encode() {
-        let encodings = vec![
+    fn test_new_standard_only() {
+        let enc = AcceptEncoding::new_standard_only(vec![(Encoding::Gzip, 1.0)]);
+        assert!(enc.is_ok());
+
+        let enc = AcceptEncoding::new_standard_only(vec![
             (Encoding::Gzip, 1.0),
-            (Encoding::Deflate, 0.5),
-            (Encoding::Br, 0.1),
-        ];
-        let enc = AcceptEncoding::new(encodings).unwrap();
-        let mut values = Vec::new();
-        enc.encode(&mut values);
+            (Encoding::Custom("foo".to_string()), 0.5),
+        ]);
+        assert!(matches!(
+            enc.err().unwrap(),
+            AcceptEncodingError::NonStandardEncoding(token) if token == "foo"
+        ));
+    }
 
-        assert_eq!(values.len(), 1);
-        assert_eq!(values[0].to_str().unwrap(), "gzip, deflate;q=0.5, br;q=0.1");
+    #[test]
+    fn test_truncate() {
+        let mut enc = AcceptEncoding::new(vec![
+            (Encoding::Gzip, 0.5),
+            (Encoding::Br, 1.0),
+            (Encoding::Deflate, 0.1),
+            (Encoding::Zstd, 0.8),
+        ])
+        .unwrap();
+
+        enc.sort_descending().truncate(2);
+        assert_eq!(enc.items(), &[(Encoding::Br, 1.0), (Encoding::Zstd, 0.8)]);
+        assert_eq!(enc.sort_state(), SortState::Descending);
+
+        enc.truncate(0);
+        assert!(enc.items().is_empty());
+        assert_eq!(enc.preferred(), None);
     }
 
     #[test]
-    fn test_empty() {
-        let encodings = vec![];
-        // constructing AcceptEncoding with empty should error
-        assert!(AcceptEncoding::new(encodings).is_err());
-        // and encode should not push anything when constructed with non-empty then cleared scenario isn't possible via API
+    fn test_explain() {
+        let enc = AcceptEncoding::parse("gzip;q=0.5, br, zstd;q=0").unwrap();
+        let report = enc.explain(&[Encoding::Gzip, Encoding::Zstd]);
+        assert_eq!(
+            report,
+            "client preferences:\n  br (q=1) not supported\n  gzip (q=0.5) supported\n  \
+             zstd (q=0) supported\nselected: gzip"
+        );
+
+        let report = enc.explain(&[Encoding::Zstd]);
+        assert!(report.ends_with("selected: identity"));
+        let enc = AcceptEncoding::parse("*;q=0").unwrap();
+        assert!(enc.explain(&[Encoding::Gzip]).contains("* (q=0) supported"));
+        assert!(enc.explain(&[Encoding::Gzip]).ends_with("406 Not Acceptable"));
     }
 
     #[test]
-    fn test_sort_ascending() {
+    fn test_quality_values_are_kept_in_range() {
+        assert_eq!(decode_header_value("gzip;q=2").unwrap(), [(Encoding::Gzip, 1.0)]);
+        assert!(matches!(
+            decode_header_value("gzip;q=NaN"),
+            Err(AcceptEncodingDecodeError::InvalidQualityValue(v)) if v == "NaN"
+        ));
+        assert!(matches!(
+            AcceptEncoding::new(vec![(Encoding::Gzip, 2.0)]),
+            Err(AcceptEncodingError::InvalidQuality(q)) if q == 2.0
+        ));
+        assert!(AcceptEncoding::new(vec![(Encoding::Gzip, QualityValue::NAN)]).is_err());
+
+        let mut enc = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0)]).unwrap();
+        enc.push(Encoding::Br, 1.5).push(Encoding::Zstd, QualityValue::NAN);
+        enc.boost(&Encoding::Gzip, QualityValue::NAN);
+        assert_eq!(
+            enc.items(),
+            &[(Encoding::Gzip, 0.0), (Encoding::Br, 1.0), (Encoding::Zstd, 0.0)]
+        );
+        assert_eq!(enc.to_header_value().unwrap(), "gzip;q=0, br, zstd;q=0");
+    }
+
+    #[test]
+    fn test_without() {
         let mut enc = AcceptEncoding::new(vec![
-            (Encoding::Gzip, 1.0),
-            (Encoding::Deflate, 0.5),
-            (Encoding::Br, 0.1),
+            (Encoding::Gzip, 0.5),
+            (Encoding::Br, 1.0),
+            (Encoding::Gzip, 0.2),
         ])
         .unwrap();
-        enc.sort_ascending();
+        enc.sort_descending();
 
+        let without_gzip = enc.without(&Encoding::Gzip);
+        assert_eq!(without_gzip.items(), &[(Encoding::Br, 1.0)]);
+        assert_eq!(without_gzip.sort_state(), SortState::Descending);
         assert_eq!(enc.items().len(), 3);
-        assert!(matches!(enc.items()[0].0, Encoding::Br));
-        assert!((enc.items()[0].1 - 0.1).abs() < QualityValue::EPSILON);
-        assert!(matches!(enc.items()[1].0, Encoding::Deflate));
-        assert!((enc.items()[1].1 - 0.5).abs() < QualityValue::EPSILON);
-        assert!(matches!(enc.items()[2].0, Encoding::Gzip));
-        assert!((enc.items()[2].1 - 1.0).abs() < QualityValue::EPSILON);
+
+        let empty = without_gzip.without(&Encoding::Br);
+        assert!(empty.items().is_empty());
+        assert_eq!(empty.preferred(), None);
     }
 
     #[test]
-    fn test_sort_descending() {
+    fn test_sort_by() {
         let mut enc = AcceptEncoding::new(vec![
-            (Encoding::Br, 0.1),
-            (Encoding::Deflate, 0.5),
+            (Encoding::Zstd, 0.5),
             (Encoding::Gzip, 1.0),
+            (Encoding::Br, 0.5),
+            (Encoding::Deflate, 1.0),
         ])
         .unwrap();
+
+        enc.sort_by(|(enc_a, q_a), (enc_b, q_b)| {
+            q_b.total_cmp(q_a)
+                .then_with(|| enc_a.to_string().cmp(&enc_b.to_string()))
+        });
+        assert_eq!(
+            enc.items(),
+            &[
+                (Encoding::Deflate, 1.0),
+                (Encoding::Gzip, 1.0),
+                (Encoding::Br, 0.5),
+                (Encoding::Zstd, 0.5),
+            ]
+        );
+        assert_eq!(enc.sort_state(), SortState::Unsorted);
+    }
+
+    #[test]
+    fn test_boost() {
+        let mut enc = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0), (Encoding::Zstd, 0.7)])
+            .unwrap();
         enc.sort_descending();
 
-        assert_eq!(enc.items().len(), 3);
-        assert!(matches!(enc.items()[0].0, Encoding::Gzip));
-        assert!((enc.items()[0].1 - 1.0).abs() < QualityValue::EPSILON);
-        assert!(matches!(enc.items()[1].0, Encoding::Deflate));
-        assert!((enc.items()[1].1 - 0.5).abs() < QualityValue::EPSILON);
-        assert!(matches!(enc.items()[2].0, Encoding::Br));
-        assert!((enc.items()[2].1 - 0.1).abs() < QualityValue::EPSILON);
+        enc.boost(&Encoding::Zstd, 0.5);
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0), (Encoding::Zstd, 1.0)]);
+        assert_eq!(enc.sort_state(), SortState::Unsorted);
+
+        enc.boost(&Encoding::Br, 0.5);
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 1.0), (Encoding::Zstd, 1.0)]);
+
+        enc.boost(&Encoding::Gzip, -2.0);
+        assert_eq!(enc.items(), &[(Encoding::Gzip, 0.0), (Encoding::Zstd, 1.0)]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_push_and_extend() {
+        let mut enc = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0)]).unwrap();
+        enc.sort_descending();
+        enc.push(Encoding::Br, 0.5);
+        assert_eq!(enc.sort_state(), SortState::Unsorted);
+
+        enc.sort_descending();
+        enc.extend([(Encoding::Zstd, 0.8), (Encoding::Identity, 0.1)]);
+        assert_eq!(enc.sort_state(), SortState::Unsorted);
+        assert_eq!(
+            enc.items(),
+            &[
+                (Encoding::Gzip, 1.0),
+                (Encoding::Br, 0.5),
+                (Encoding::Zstd, 0.8),
+                (Encoding::Identity, 0.1),
+            ]
+        );
+    }
 
     #[test]
-    fn decode_header_value_parses_list_and_qualities() {
-        let parsed = decode_header_value("gzip, deflate;q=0.5, br;q=0.100").unwrap();
-        assert_eq!(parsed.len(), 3);
-        assert!(matches!(parsed[0].0, Encoding::Gzip));
-        assert!((parsed[0].1 - 1.0).abs() < QualityValue::EPSILON);
-        assert!(matches!(parsed[1].0, Encoding::Deflate));
-        assert!((parsed[1].1 - 0.5).abs() < QualityValue::EPSILON);
-        assert!(matches!(parsed[2].0, Encoding::Br));
-        assert!((parsed[2].1 - 0.1).abs() < QualityValue::EPSILON);
+    fn test_extend_clamps_quality() {
+        let mut enc = AcceptEncoding::new(vec![(Encoding::Zstd, 0.5)]).unwrap();
+        enc.extend([(Encoding::Gzip, 2.0), (Encoding::Br, QualityValue::NAN)]);
+        assert_eq!(
+            enc.items(),
+            &[(Encoding::Zstd, 0.5), (Encoding::Gzip, 1.0), (Encoding::Br, 0.0)]
+        );
+        assert_eq!(enc.to_header_value().unwrap(), "zstd;q=0.5, gzip, br;q=0");
     }
 
     #[test]
-    fn decode_header_value_handles_errors() {
-        // empty tuple
-        assert!(matches!(
-            decode_header_value(" , gzip"),
-            Err(AcceptEncodingDecodeError::EmptyEncodingWeightTuple)
-        ));
-        // empty name
-        assert!(matches!(
-            decode_header_value(";q=1.0"),
-            Err(AcceptEncodingDecodeError::EmptyEncodingName)
-        ));
-        // invalid q
+    fn test_default_is_browser_like() {
+        let enc = AcceptEncoding::default();
+        assert!(enc.contains(&Encoding::Br));
+        assert_eq!(
+            encode_header_value(enc.items()).unwrap(),
+            "gzip, deflate, br, zstd"
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Gzip, 0.0), (Encoding::Wildcard, 1.0)])
+            .unwrap();
+        assert!(enc.contains(&Encoding::Gzip));
+        assert!(enc.contains(&Encoding::Wildcard));
+        assert!(!enc.contains(&Encoding::Br));
+    }
+
+    #[test]
+    fn test_rejects_explicit() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Gzip, 0.0), (Encoding::Br, 0.5)]).unwrap();
+        assert!(enc.rejects(&Encoding::Gzip));
+        assert!(!enc.rejects(&Encoding::Br));
+        // Not listed and no wildcard
+        assert!(!enc.rejects(&Encoding::Zstd));
+    }
+
+    #[test]
+    fn test_rejects_wildcard() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Wildcard, 0.0)])
+            .unwrap();
+        assert!(enc.rejects(&Encoding::Gzip));
+        assert!(enc.rejects(&Encoding::Identity));
+        // An explicit positive entry overrides the wildcard
+        assert!(!enc.rejects(&Encoding::Br));
+    }
+
+    #[test]
+    fn test_pairs_round_trip() {
+        let pairs = vec![
+            ("gzip".to_string(), 1.0),
+            ("br".to_string(), 0.5),
+            ("x-custom".to_string(), 0.1),
+        ];
+
+        let enc = AcceptEncoding::from_pairs(pairs.clone()).unwrap();
+        assert!(matches!(enc.items()[0].0, Encoding::Gzip));
+        assert!(matches!(enc.items()[1].0, Encoding::Br));
+        assert!(matches!(&enc.items()[2].0, Encoding::Custom(s) if s == "x-custom"));
+        assert_eq!(enc.to_pairs(), pairs);
+
+        let converted = AcceptEncoding::try_from(pairs.clone()).unwrap();
+        let back: Vec<(String, QualityValue)> = converted.into();
+        assert_eq!(back, pairs);
+    }
+
+    #[test]
+    fn test_from_pairs_empty() {
         assert!(matches!(
-            decode_header_value("gzip;q=abc"),
-            Err(AcceptEncodingDecodeError::InvalidQualityValue(_))
+            AcceptEncoding::try_from(Vec::<(String, QualityValue)>::new()),
+            Err(AcceptEncodingError::EmptyEncodings)
         ));
-        // unexpected directive
+    }
+
+    #[test]
+    fn test_with_server_fallback_client_already_acceptable() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Gzip, 0.5)]).unwrap();
+        let fallback = enc.with_server_fallback(Encoding::Gzip, 0.1);
+        assert_eq!(fallback.items(), enc.items());
+    }
+
+    #[test]
+    fn test_with_server_fallback_needs_fallback() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Gzip, 0.0)]).unwrap();
+        let fallback = enc.with_server_fallback(Encoding::Gzip, 0.1);
+        assert_eq!(
+            fallback.items(),
+            &[
+                (Encoding::Br, 1.0),
+                (Encoding::Gzip, 0.0),
+                (Encoding::Gzip, 0.1)
+            ]
+        );
         assert!(matches!(
-            decode_header_value("gzip;foo=bar"),
-            Err(AcceptEncodingDecodeError::UnexpectedDirective(s)) if s=="foo=bar"
+            fallback.preferred_allowed([Encoding::Gzip].iter()),
+            Some(&Encoding::Gzip)
         ));
+        // The original is left untouched
+        assert_eq!(enc.items().len(), 2);
     }
 
     #[test]
-    fn encode_header_value_formats_properly() {
-        let value = encode_header_value(&[
-            (Encoding::Gzip, 1.0),
+    fn test_negotiate_default_priority_breaks_ties() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0), (Encoding::Zstd, 1.0)]).unwrap();
+        assert_eq!(
+            enc.negotiate(&[Encoding::Gzip, Encoding::Zstd]),
+            NegotiationResult::Selected(Encoding::Zstd)
+        );
+        assert_eq!(
+            enc.negotiate(&[Encoding::Gzip]),
+            NegotiationResult::Selected(Encoding::Gzip)
+        );
+    }
+
+    #[test]
+    fn test_negotiate_client_quality_wins_over_priority() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0), (Encoding::Zstd, 0.5)]).unwrap();
+        assert_eq!(
+            enc.negotiate(&[Encoding::Zstd, Encoding::Gzip]),
+            NegotiationResult::Selected(Encoding::Gzip)
+        );
+    }
+
+    #[test]
+    fn test_negotiate_wildcard_and_identity() {
+        let enc =
+            AcceptEncoding::new(vec![(Encoding::Gzip, 0.0), (Encoding::Wildcard, 0.5)]).unwrap();
+        assert_eq!(
+            enc.negotiate(&[Encoding::Gzip, Encoding::Br]),
+            NegotiationResult::Selected(Encoding::Br)
+        );
+        assert_eq!(enc.negotiate(&[Encoding::Gzip]), NegotiationResult::Identity);
+
+        let enc =
+            AcceptEncoding::new(vec![(Encoding::Gzip, 0.5), (Encoding::Identity, 1.0)]).unwrap();
+        assert_eq!(enc.negotiate(&[Encoding::Gzip]), NegotiationResult::Identity);
+
+        let enc =
+            AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Wildcard, 0.0)]).unwrap();
+        assert_eq!(enc.negotiate(&[Encoding::Gzip]), NegotiationResult::NotAcceptable);
+    }
+
+    #[test]
+    fn test_to_sorted_descending_takes_fast_path() {
+        let enc = AcceptEncoding::new(vec![
             (Encoding::Deflate, 0.5),
-            (Encoding::Br, 0.1),
+            (Encoding::Br, 1.0),
+            (Encoding::Gzip, 1.0),
         ])
         .unwrap();
-        assert_eq!(value, "gzip, deflate;q=0.5, br;q=0.1");
+        assert_eq!(enc.sort_state(), SortState::Unsorted);
+
+        let sorted = enc.to_sorted_descending();
+        assert_eq!(sorted.sort_state(), SortState::Descending);
+        assert_eq!(
+            sorted.items(),
+            &[
+                (Encoding::Br, 1.0),
+                (Encoding::Gzip, 1.0),
+                (Encoding::Deflate, 0.5)
+            ]
+        );
+        // The original is left untouched
+        assert_eq!(enc.items()[0].0, Encoding::Deflate);
+        assert_eq!(enc.sort_state(), SortState::Unsorted);
     }
 
     #[test]
-    fn encode_header_value_omits_q_for_one_and_trims_trailing_zeros() {
-        let value = encode_header_value(&[
+    fn test_scores_first_matches_negotiate() {
+        let enc = AcceptEncoding::new(vec![
             (Encoding::Gzip, 1.0),
-            (Encoding::Deflate, 0.5000),
-            (Encoding::Br, 0.1000),
+            (Encoding::Br, 0.9),
+            (Encoding::Deflate, 0.0),
+            (Encoding::Identity, 0.5),
+            (Encoding::Wildcard, 0.2),
         ])
         .unwrap();
-        // ensures trimming and omission of q=1
-        assert_eq!(value, "gzip, deflate;q=0.5, br;q=0.1");
+        let server = [
+            (Encoding::Deflate, 1.0),
+            (Encoding::Gzip, 0.5),
+            (Encoding::Br, 1.0),
+            (Encoding::Zstd, 1.0),
+        ];
+
+        let scores = enc.scores(&server);
+        assert_eq!(
+            scores,
+            vec![
+                (Encoding::Br, 0.9),
+                (Encoding::Gzip, 0.5),
+                (Encoding::Identity, 0.5),
+                (Encoding::Zstd, 0.2),
+            ]
+        );
+        assert_eq!(
+            enc.negotiate_weighted(&server),
+            NegotiationResult::Selected(scores[0].0.clone())
+        );
+
+        let unweighted: Vec<(Encoding, QualityValue)> =
+            server.iter().map(|(e, _)| (e.clone(), 1.0)).collect();
+        let servers: Vec<Encoding> = server.iter().map(|(e, _)| e.clone()).collect();
+        assert_eq!(
+            enc.negotiate(&servers),
+            NegotiationResult::Selected(enc.scores(&unweighted)[0].0.clone())
+        );
     }
 
     #[test]
-    fn encode_header_value_errors_on_empty() {
-        assert!(matches!(
-            encode_header_value(&[]),
-            Err(AcceptEncodingEncodeError::EmptyEncodings)
-        ));
+    fn test_preferred_allowed_biased_flips_to_cached() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Gzip, 0.9)]).unwrap();
+        let server = [(Encoding::Br, 1.0), (Encoding::Gzip, 1.0)];
+
+        assert_eq!(
+            enc.preferred_allowed_biased(&server, &Encoding::Gzip, 0.0),
+            Some(Encoding::Br)
+        );
+        assert_eq!(
+            enc.preferred_allowed_biased(&server, &Encoding::Gzip, 0.2),
+            Some(Encoding::Gzip)
+        );
+        // Not enough to overcome a large client preference
+        let enc = AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Gzip, 0.5)]).unwrap();
+        assert_eq!(
+            enc.preferred_allowed_biased(&server, &Encoding::Gzip, 0.2),
+            Some(Encoding::Br)
+        );
+        // A rejected encoding never gets the bonus
+        let enc = AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Gzip, 0.0)]).unwrap();
+        assert_eq!(
+            enc.preferred_allowed_biased(&server, &Encoding::Gzip, 2.0),
+            Some(Encoding::Br)
+        );
     }
 
     #[test]
@@ -526,6 +3071,33 @@ mod tests {
         assert!(matches!(enc.preferred(), Some(&Encoding::Gzip)));
     }
 
+    #[test]
+    fn test_preferred_skips_rejected_in_all_sort_states() {
+        let mut enc = AcceptEncoding::new(vec![
+            (Encoding::Gzip, 0.0),
+            (Encoding::Br, 0.0),
+            (Encoding::Identity, 0.0),
+        ])
+        .unwrap();
+        assert_eq!(enc.preferred(), None);
+        enc.sort_ascending();
+        assert_eq!(enc.preferred(), None);
+        enc.sort_descending();
+        assert_eq!(enc.preferred(), None);
+
+        let mut enc = AcceptEncoding::new(vec![
+            (Encoding::Gzip, 0.0),
+            (Encoding::Br, 0.3),
+            (Encoding::Deflate, 0.0),
+        ])
+        .unwrap();
+        assert_eq!(enc.preferred(), Some(&Encoding::Br));
+        enc.sort_ascending();
+        assert_eq!(enc.preferred(), Some(&Encoding::Br));
+        enc.sort_descending();
+        assert_eq!(enc.preferred(), Some(&Encoding::Br));
+    }
+
     #[test]
     fn test_preferred_allowed_unsorted() {
         let enc = AcceptEncoding::new(vec![
@@ -535,7 +3107,7 @@ mod tests {
         ])
         .unwrap();
 
-        let allowed = vec![Encoding::Deflate, Encoding::Br];
+        let allowed = [Encoding::Deflate, Encoding::Br];
         assert!(matches!(
             enc.preferred_allowed(allowed.iter()),
             Some(&Encoding::Deflate)
@@ -552,7 +3124,7 @@ mod tests {
         .unwrap();
         enc.sort_descending();
 
-        let allowed = vec![Encoding::Deflate, Encoding::Br];
+        let allowed = [Encoding::Deflate, Encoding::Br];
         assert!(matches!(
             enc.preferred_allowed(allowed.iter()),
             Some(&Encoding::Deflate)
@@ -569,13 +3141,93 @@ mod tests {
         .unwrap();
         enc.sort_ascending();
 
-        let allowed = vec![Encoding::Deflate, Encoding::Br];
+        let allowed = [Encoding::Deflate, Encoding::Br];
         assert!(matches!(
             enc.preferred_allowed(allowed.iter()),
             Some(&Encoding::Deflate)
         ));
     }
 
+    #[test]
+    fn test_preference_list() {
+        let enc = AcceptEncoding::new(vec![
+            (Encoding::Gzip, 0.5),
+            (Encoding::Br, 1.0),
+            (Encoding::Gzip, 0.8),
+            (Encoding::Deflate, 0.0),
+            (Encoding::Wildcard, 0.3),
+        ])
+        .unwrap();
+        let universe = [Encoding::Zstd, Encoding::Deflate, Encoding::Gzip];
+
+        let list: Vec<(&Encoding, QualityValue)> = enc.preference_list(&universe).collect();
+
+        // Manually: collapse duplicates, expand the wildcard, drop q=0, sort
+        let mut manual: Vec<(Encoding, QualityValue)> = enc
+            .as_map()
+            .into_iter()
+            .filter(|(e, _)| *e != Encoding::Wildcard)
+            .collect();
+        manual.push((Encoding::Zstd, 0.3));
+        manual.retain(|(_, q)| *q > 0.0);
+        manual.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let list: Vec<(Encoding, QualityValue)> =
+            list.into_iter().map(|(e, q)| (e.clone(), q)).collect();
+        assert_eq!(list, manual);
+        assert_eq!(
+            list,
+            vec![
+                (Encoding::Br, 1.0),
+                (Encoding::Gzip, 0.8),
+                (Encoding::Zstd, 0.3)
+            ]
+        );
+        assert_eq!(enc.preference_list(&[]).len(), 2);
+    }
+
+    #[test]
+    fn test_preferred_allowed_owned() {
+        let enc = AcceptEncoding::new(vec![
+            (Encoding::Gzip, 0.8),
+            (Encoding::Br, 1.0),
+            (Encoding::Zstd, 0.5),
+        ])
+        .unwrap();
+
+        let preferred = enc.preferred_allowed_owned(
+            ["gzip", "zstd"]
+                .into_iter()
+                .map(|token| token.parse::<Encoding>().unwrap()),
+        );
+        assert_eq!(preferred, Some(Encoding::Gzip));
+        assert_eq!(enc.preferred_allowed_owned([Encoding::Deflate]), None);
+    }
+
+    #[test]
+    fn test_preferred_allowed_with_quality_returns_client_q() {
+        let mut enc = AcceptEncoding::new(vec![
+            (Encoding::Br, 0.5),
+            (Encoding::Gzip, 1.0),
+            (Encoding::Deflate, 0.8),
+        ])
+        .unwrap();
+
+        let allowed = [Encoding::Deflate, Encoding::Br];
+        let (selected, q) = enc.preferred_allowed_with_quality(allowed.iter()).unwrap();
+        assert_eq!(selected, &Encoding::Deflate);
+        assert!((q - 0.8).abs() < QualityValue::EPSILON);
+
+        enc.sort_ascending();
+        let allowed = [Encoding::Br];
+        let (selected, q) = enc.preferred_allowed_with_quality(allowed.iter()).unwrap();
+        assert_eq!(selected, &Encoding::Br);
+        assert!((q - 0.5).abs() < QualityValue::EPSILON);
+
+        let allowed = [Encoding::Identity];
+        assert!(enc.preferred_allowed_with_quality(allowed.iter()).is_none());
+    }
+
     #[test]
     fn test_preferred_allowed_quality_zero() {
         let enc = AcceptEncoding::new(vec![
@@ -585,8 +3237,8 @@ mod tests {
         ])
         .unwrap();
 
-        let allowed = vec![Encoding::Deflate, Encoding::Br];
-        assert!(matches!(enc.preferred_allowed(allowed.iter()), None));
+        let allowed = [Encoding::Deflate, Encoding::Br];
+        assert!(enc.preferred_allowed(allowed.iter()).is_none());
     }
 
     #[test]
@@ -598,8 +3250,8 @@ mod tests {
         ])
         .unwrap();
 
-        let allowed = vec![Encoding::Identity];
-        assert!(matches!(enc.preferred_allowed(allowed.iter()), None));
+        let allowed = [Encoding::Identity];
+        assert!(enc.preferred_allowed(allowed.iter()).is_none());
     }
 
     #[test]
@@ -611,13 +3263,13 @@ mod tests {
         ])
         .unwrap();
 
-        let allowed = vec![(Encoding::Deflate, 1.0), (Encoding::Br, 0.8)];
+        let allowed = [(Encoding::Deflate, 1.0), (Encoding::Br, 0.8)];
         assert!(matches!(
             enc.preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Deflate)
         ));
 
-        let allowed = vec![(Encoding::Deflate, 0.5), (Encoding::Br, 1.0)];
+        let allowed = [(Encoding::Deflate, 0.5), (Encoding::Br, 1.0)];
         assert!(matches!(
             enc.preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Deflate)
@@ -634,14 +3286,14 @@ mod tests {
             .unwrap();
         enc.sort_ascending();
 
-        let allowed = vec![(Encoding::Deflate, 1.0), (Encoding::Br, 0.8)];
+        let allowed = [(Encoding::Deflate, 1.0), (Encoding::Br, 0.8)];
         assert!(matches!(
             enc.preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Deflate)
         ));
 
         // When server prefers Br with high weight
-        let allowed = vec![(Encoding::Deflate, 0.5), (Encoding::Br, 1.0)];
+        let allowed = [(Encoding::Deflate, 0.5), (Encoding::Br, 1.0)];
         assert!(matches!(
             enc.preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Deflate)
@@ -658,14 +3310,14 @@ mod tests {
             .unwrap();
         enc.sort_descending();
 
-        let allowed = vec![(Encoding::Deflate, 1.0), (Encoding::Br, 0.8)];
+        let allowed = [(Encoding::Deflate, 1.0), (Encoding::Br, 0.8)];
         assert!(matches!(
             enc.preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Deflate)
         ));
 
         // When server prefers Br with high weight
-        let allowed = vec![(Encoding::Deflate, 0.5), (Encoding::Br, 1.0)];
+        let allowed = [(Encoding::Deflate, 0.5), (Encoding::Br, 1.0)];
         assert!(matches!(
             enc.preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Deflate)
@@ -681,7 +3333,7 @@ mod tests {
         ])
         .unwrap();
 
-        let allowed = vec![(Encoding::Deflate, 1.0), (Encoding::Br, 1.0)];
+        let allowed = [(Encoding::Deflate, 1.0), (Encoding::Br, 1.0)];
         assert!(matches!(
             enc.preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Br)
@@ -697,7 +3349,7 @@ mod tests {
         ])
             .unwrap();
 
-        let allowed = vec![(Encoding::Deflate, 1.0), (Encoding::Br, 1.0)];
+        let allowed = [(Encoding::Deflate, 1.0), (Encoding::Br, 1.0)];
         assert!(matches!(
             enc.sort_ascending().preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Br)
@@ -713,10 +3365,69 @@ mod tests {
         ])
             .unwrap();
 
-        let allowed = vec![(Encoding::Deflate, 1.0), (Encoding::Br, 1.0)];
+        let allowed = [(Encoding::Deflate, 1.0), (Encoding::Br, 1.0)];
         assert!(matches!(
             enc.sort_descending().preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
             Some(&Encoding::Br)
         ));
     }
+
+    #[test]
+    fn test_preferred_allowed_weighted_wildcard_weight_covers_unlisted() {
+        let allowed = [(Encoding::Gzip, 0.5), (Encoding::Wildcard, 0.3)];
+        let allowed = || allowed.iter().map(|(e, q)| (e, *q));
+
+        // Equal client preference: the explicit gzip weight beats br covered by `*`,
+        // whatever the sort state
+        let enc = AcceptEncoding::parse("br, gzip, deflate;q=0.5").unwrap();
+        assert_eq!(enc.preferred_allowed_weighted(allowed()), Some(&Encoding::Gzip));
+        let descending = enc.to_sorted_descending();
+        assert_eq!(descending.preferred_allowed_weighted(allowed()), Some(&Encoding::Gzip));
+        let mut ascending = enc.clone();
+        ascending.sort_ascending();
+        assert_eq!(ascending.preferred_allowed_weighted(allowed()), Some(&Encoding::Gzip));
+        let server = [(Encoding::Gzip, 0.5), (Encoding::Wildcard, 0.3)];
+        assert_eq!(enc.negotiate_weighted(&server), NegotiationResult::Selected(Encoding::Gzip));
+
+        // The client preference still comes first
+        let enc = AcceptEncoding::parse("br, gzip;q=0.8").unwrap();
+        assert_eq!(enc.preferred_allowed_weighted(allowed()), Some(&Encoding::Br));
+        let descending = enc.to_sorted_descending();
+        assert_eq!(descending.preferred_allowed_weighted(allowed()), Some(&Encoding::Br));
+
+        // A wildcard weight of zero allows nothing beyond the listed encodings
+        let allowed = [(Encoding::Gzip, 0.5), (Encoding::Wildcard, 0.0)];
+        let enc = AcceptEncoding::parse("br").unwrap();
+        assert_eq!(
+            enc.preferred_allowed_weighted(allowed.iter().map(|(e, q)| (e, *q))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_preferred_allowed_client_wildcard_covers_unlisted() {
+        let allowed = [Encoding::Gzip];
+        let enc = AcceptEncoding::parse("*").unwrap();
+        assert_eq!(enc.preferred_allowed(allowed.iter()), Some(&Encoding::Gzip));
+        assert_eq!(enc.negotiate(&allowed), NegotiationResult::Selected(Encoding::Gzip));
+
+        // Explicit entries win over `*`, whatever the sort state
+        let allowed = [Encoding::Gzip, Encoding::Br, Encoding::Zstd];
+        let mut enc = AcceptEncoding::parse("gzip;q=0, *;q=0.5, br;q=0.4").unwrap();
+        assert_eq!(
+            enc.preferred_allowed_with_quality(allowed.iter()),
+            Some((&Encoding::Zstd, 0.5))
+        );
+        assert_eq!(enc.negotiate(&allowed), NegotiationResult::Selected(Encoding::Zstd));
+        enc.sort_descending();
+        assert_eq!(enc.preferred_allowed(allowed.iter()), Some(&Encoding::Zstd));
+        enc.sort_ascending();
+        assert_eq!(enc.preferred_allowed(allowed.iter()), Some(&Encoding::Zstd));
+
+        // `*;q=0` covers nothing
+        let enc = AcceptEncoding::parse("br;q=0.5, *;q=0").unwrap();
+        assert_eq!(enc.preferred_allowed(allowed.iter()), Some(&Encoding::Br));
+        let allowed = [Encoding::Gzip];
+        assert_eq!(enc.preferred_allowed(allowed.iter()), None);
+    }
 }
@@ -0,0 +1,94 @@
+//! The `ContentCodec` trait for applying an `Encoding` to a body, behind the
+//! `codecs` feature.
+//!
+//! `IdentityCodec` is built in and `codec_for` looks up the built-in codec for a
+//! negotiated encoding. A built-in `GzipCodec` needs `flate2`, which is not a
+//! dependency yet; until it is, compressing codecs implement `ContentCodec`
+//! outside this crate, see the `codecs` example.
+
+use crate::encoding::Encoding;
+use std::io;
+
+/// Encodes and decodes bodies with a single content coding
+pub trait ContentCodec: Send + Sync {
+    /// Returns the content coding this codec implements
+    fn encoding(&self) -> Encoding;
+
+    /// Encodes `input`, e.g. compresses a response body
+    fn encode(&self, input: &[u8]) -> io::Result<Vec<u8>>;
+
+    /// Decodes `input`, e.g. decompresses a request body
+    fn decode(&self, input: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+/// Passes bodies through unchanged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IdentityCodec;
+
+impl ContentCodec for IdentityCodec {
+    fn encoding(&self) -> Encoding {
+        Encoding::Identity
+    }
+
+    fn encode(&self, input: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(input.to_vec())
+    }
+
+    fn decode(&self, input: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(input.to_vec())
+    }
+}
+
+/// Returns the built-in codec for a negotiated `encoding`, if there is one
+pub fn codec_for(encoding: &Encoding) -> Option<Box<dyn ContentCodec>> {
+    match encoding {
+        Encoding::Identity => Some(Box::new(IdentityCodec)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_codec_round_trip() {
+        let codec = codec_for(&Encoding::Identity).unwrap();
+        assert_eq!(codec.encoding(), Encoding::Identity);
+        let encoded = codec.encode(b"hello").unwrap();
+        assert_eq!(encoded, b"hello");
+        assert_eq!(codec.decode(&encoded).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_codec_for_unsupported() {
+        assert!(codec_for(&Encoding::Gzip).is_none());
+        assert!(codec_for(&Encoding::Wildcard).is_none());
+    }
+
+    struct ReverseCodec;
+
+    impl ContentCodec for ReverseCodec {
+        fn encoding(&self) -> Encoding {
+            Encoding::Custom("x-reverse".to_string())
+        }
+
+        fn encode(&self, input: &[u8]) -> io::Result<Vec<u8>> {
+            Ok(input.iter().rev().copied().collect())
+        }
+
+        fn decode(&self, input: &[u8]) -> io::Result<Vec<u8>> {
+            self.encode(input)
+        }
+    }
+
+    #[test]
+    fn test_codec_as_trait_object() {
+        let codecs: Vec<Box<dyn ContentCodec>> = vec![Box::new(ReverseCodec)];
+        let codec = codecs.iter().find(|c| c.encoding() == "x-reverse".parse().unwrap());
+        let codec = codec.unwrap();
+        let encoded = codec.encode(b"hello").unwrap();
+        assert_eq!(encoded, b"olleh");
+        assert_eq!(codec.decode(&encoded).unwrap(), b"hello");
+    }
+}
@@ -1,22 +1,241 @@
+use crate::accept_encoding::AcceptEncoding;
 use crate::encoding::Encoding;
+use crate::negotiation::{self, NegotiationResult};
 use std::cmp::PartialEq;
+#[cfg(feature = "http")]
 use std::str::FromStr;
+use thiserror::Error;
+
+/// Error type for constructing `ContentEncoding`
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ContentEncodingError {
+    #[error("wildcard is only meaningful in Accept-Encoding")]
+    WildcardNotAllowed,
+    #[error("identity cannot be part of a stack of encodings")]
+    IdentityInStack,
+    #[error("encodings cannot be empty")]
+    EmptyEncodings,
+    #[error("encoding rejected by the client: {0}")]
+    RejectedByClient(Encoding),
+}
 
 /// A wrapper type for content encoding that represents the compression or encoding
 /// scheme used in an HTTP message body. This is used in HTTP's Content-Encoding header.
+///
+/// Multiple encodings are stored in the order they were applied, matching the
+/// header's list order: `gzip, br` means gzip was applied first and br last.
+///
+/// Values order lexicographically over the encoding stack, using the `Encoding`
+/// order for each layer. A stack sorts directly after any stack it extends, so
+/// `gzip` comes before `gzip, br`, which comes before `br`.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ContentEncoding(Encoding);
+pub struct ContentEncoding(Vec<Encoding>);
 
 impl ContentEncoding {
     /// Create a new ContentEncoding with the specified encoding
-    pub fn new(encoding: Encoding) -> Self {
-        ContentEncoding(encoding)
+    ///
+    /// `Encoding::Wildcard` is rejected, as it is only meaningful in Accept-Encoding.
+    pub fn new(encoding: Encoding) -> Result<Self, ContentEncodingError> {
+        if matches!(encoding, Encoding::Wildcard) {
+            return Err(ContentEncodingError::WildcardNotAllowed);
+        }
+        Ok(ContentEncoding(vec![encoding]))
+    }
+
+    /// Create a new ContentEncoding like `new`, but only if the client does not
+    /// reject `encoding` with `q=0` according to `AcceptEncoding::rejects`.
+    pub fn try_new_for(
+        encoding: Encoding,
+        accept: &AcceptEncoding,
+    ) -> Result<Self, ContentEncodingError> {
+        if accept.rejects(&encoding) {
+            return Err(ContentEncodingError::RejectedByClient(encoding));
+        }
+        Self::new(encoding)
+    }
+
+    /// Create a ContentEncoding from encodings in the order they were applied.
+    ///
+    /// Rejects an empty list and `Encoding::Wildcard` anywhere. `Encoding::Identity`
+    /// is only allowed on its own, as it is meaningless within a stack.
+    pub fn from_encodings(encodings: Vec<Encoding>) -> Result<Self, ContentEncodingError> {
+        if encodings.is_empty() {
+            return Err(ContentEncodingError::EmptyEncodings);
+        }
+        if encodings.iter().any(|enc| matches!(enc, Encoding::Wildcard)) {
+            return Err(ContentEncodingError::WildcardNotAllowed);
+        }
+        if encodings.len() > 1 && encodings.iter().any(Encoding::is_identity) {
+            return Err(ContentEncodingError::IdentityInStack);
+        }
+        Ok(ContentEncoding(encodings))
+    }
+
+    /// Create a ContentEncoding meaning no encoding at all.
+    ///
+    /// Encoding it as a header emits nothing, so the header is omitted.
+    pub fn identity() -> Self {
+        ContentEncoding(vec![Encoding::Identity])
     }
 
-    /// Get the encoding value
-    pub fn encoding(&self) -> &Encoding {
+    /// Get the outermost (last-applied) encoding value
+    pub fn encoding(&self) -> Option<&Encoding> {
+        self.0.last()
+    }
+
+    /// Get all encodings in the order they were applied
+    #[inline]
+    pub fn encodings(&self) -> &[Encoding] {
         &self.0
     }
+
+    /// Returns the token of each encoding in the order they were applied,
+    /// e.g. `["gzip", "br"]`
+    pub fn as_str_list(&self) -> Vec<&str> {
+        self.0.iter().map(Encoding::as_str).collect()
+    }
+
+    /// Returns true if no encodings are left
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Applies `encoding` as a new outermost layer.
+    ///
+    /// `Encoding::Identity` and `Encoding::Wildcard` are rejected as layers. Pushing
+    /// onto `identity()` replaces it, as identity means no encoding at all.
+    pub fn push_layer(&mut self, encoding: Encoding) -> Result<(), ContentEncodingError> {
+        match encoding {
+            Encoding::Wildcard => return Err(ContentEncodingError::WildcardNotAllowed),
+            Encoding::Identity => return Err(ContentEncodingError::IdentityInStack),
+            _ => {}
+        }
+        self.0.retain(|enc| !enc.is_identity());
+        self.0.push(encoding);
+        Ok(())
+    }
+
+    /// Removes and returns the outermost (last-applied) encoding, leaving the rest.
+    ///
+    /// Stripping a single-encoding value leaves it empty. An empty value displays
+    /// as an empty string and `encode` emits no header value for it.
+    pub fn strip_outermost(&mut self) -> Option<Encoding> {
+        self.0.pop()
+    }
+
+    /// Removes all encodings and returns them in the order they have to be
+    /// removed, outermost first.
+    pub fn strip_all(&mut self) -> Vec<Encoding> {
+        let mut stripped = std::mem::take(&mut self.0);
+        stripped.reverse();
+        stripped
+    }
+
+    /// Returns a copy without redundant identity layers.
+    ///
+    /// Identity is a no-op, so `Content-Encoding: identity, gzip` is semantically
+    /// just `gzip`. A value consisting only of identity normalizes to `identity()`.
+    /// Compare normalized values to treat both forms as equal.
+    pub fn normalized(&self) -> ContentEncoding {
+        let mut encodings = self.0.clone();
+        strip_redundant_identity(&mut encodings);
+        ContentEncoding(encodings)
+    }
+
+    /// Returns each encoding layer in the order applied, paired with whether the
+    /// client accepts it, e.g. for a proxy deciding whether to forward the body.
+    ///
+    /// A layer is accepted if the client assigns it a positive quality value,
+    /// directly or via a wildcard. Identity is accepted unless explicitly rejected.
+    pub fn acceptable_layers<'a>(
+        &'a self,
+        accept: &AcceptEncoding,
+    ) -> impl Iterator<Item = (&'a Encoding, bool)> {
+        let identity_acceptable = negotiation::identity_acceptable(accept);
+        let accepted: Vec<bool> = self
+            .0
+            .iter()
+            .map(|enc| match enc {
+                Encoding::Identity => identity_acceptable,
+                _ => negotiation::effective_quality(accept, enc).is_some_and(|q| q > 0.0),
+            })
+            .collect();
+        self.0.iter().zip(accepted)
+    }
+
+    /// Returns whether `first_bytes` of a body plausibly carry the outermost encoding.
+    ///
+    /// This is a cheap, best-effort sanity check based on known magic numbers, meant
+    /// to detect mislabeled bodies before handing them to a decompressor. It is
+    /// conservative and only returns false when the bytes definitely do not match:
+    /// encodings without a magic number (br, lzma, deflate which is sometimes sent as
+    /// raw deflate, custom encodings), identity and too short inputs yield true.
+    pub fn looks_like(&self, first_bytes: &[u8]) -> bool {
+        let magic: &[u8] = match self.0.last() {
+            Some(Encoding::Gzip) => &[0x1f, 0x8b],
+            Some(Encoding::Compress) => &[0x1f, 0x9d],
+            Some(Encoding::Zstd) => &[0x28, 0xb5, 0x2f, 0xfd],
+            Some(Encoding::Xz) => &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+            Some(Encoding::Bzip2) => b"BZh",
+            Some(Encoding::Lz4) => &[0x04, 0x22, 0x4d, 0x18],
+            Some(Encoding::Zlib) => return looks_like_zlib(first_bytes),
+            _ => return true,
+        };
+        first_bytes.len() < magic.len() || first_bytes.starts_with(magic)
+    }
+}
+
+/// Checks the two byte zlib header: deflate compression method and a valid check value
+fn looks_like_zlib(first_bytes: &[u8]) -> bool {
+    match first_bytes {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && ((u16::from(*cmf) << 8) | u16::from(*flg)) % 31 == 0,
+        _ => true,
+    }
+}
+
+/// Removes identity layers from a stack, keeping a single identity if nothing else
+/// is left
+fn strip_redundant_identity(encodings: &mut Vec<Encoding>) {
+    if encodings.iter().any(|enc| !enc.is_identity()) {
+        encodings.retain(|enc| !enc.is_identity());
+    } else {
+        encodings.truncate(1);
+    }
+}
+
+/// A `ContentEncoding` equals an `Encoding` if it consists of exactly that encoding.
+/// Multi-encoding values never compare equal to a single `Encoding`.
+impl PartialEq<Encoding> for ContentEncoding {
+    fn eq(&self, other: &Encoding) -> bool {
+        matches!(self.0.as_slice(), [encoding] if encoding == other)
+    }
+}
+
+/// Turns a negotiation outcome into the Content-Encoding header to send.
+///
+/// Only `Selected` yields a header. `Identity` means the header is omitted, and
+/// `NotAcceptable` calls for a 406 response rather than a Content-Encoding.
+impl From<NegotiationResult> for Option<ContentEncoding> {
+    fn from(result: NegotiationResult) -> Self {
+        match result {
+            NegotiationResult::Selected(encoding) => ContentEncoding::new(encoding).ok(),
+            NegotiationResult::Identity | NegotiationResult::NotAcceptable => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ContentEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, encoding) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            std::fmt::Display::fmt(encoding, f)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "http_crates")]
@@ -25,36 +244,169 @@ impl headers::Header for ContentEncoding {
         &http::header::CONTENT_ENCODING
     }
 
+    /// Decodes the header, stacking the encodings of all comma-separated values
+    /// and header lines in order. Empty list elements are ignored, a wildcard is
+    /// rejected. Identity layers are dropped as in `ContentEncoding::normalized`.
     fn decode<'i, I>(values: &mut I) -> Result<Self, headers::Error>
     where
         Self: Sized,
         I: Iterator<Item = &'i headers::HeaderValue>,
     {
-        let mut found_encoding_optional = None;
+        let mut encodings = Vec::new();
         for header_value in values {
-            let encoding = header_value
-                .to_str()
-                .map_err(|_| headers::Error::invalid())
-                // Infallible
-                .map(|e| Encoding::from_str(e).unwrap())?;
-
-            if let Some(found_encoding) = &found_encoding_optional
-                && encoding != *found_encoding
-            {
-                return Err(headers::Error::invalid());
-            }
-            // Infallible
-            let _ = found_encoding_optional.insert(encoding);
+            decode_header_value_into(header_value, &mut encodings)
+                .map_err(|_| headers::Error::invalid())?;
         }
 
-        match found_encoding_optional {
-            None => Err(headers::Error::invalid()),
-            Some(encoding) => Ok(ContentEncoding(encoding)),
+        if encodings.is_empty() {
+            return Err(headers::Error::invalid());
         }
+        strip_redundant_identity(&mut encodings);
+        Ok(ContentEncoding(encodings))
     }
 
+    /// Encodes all encodings into a single comma-joined header value, e.g. `gzip, br`.
+    /// Use `ContentEncoding::encode_multiline` to emit one header line per encoding.
+    ///
+    /// Nothing is emitted for an empty value or identity alone, as a server should
+    /// omit the header instead of sending `Content-Encoding: identity`.
     fn encode<E: Extend<headers::HeaderValue>>(&self, values: &mut E) {
-        values.extend(headers::HeaderValue::from_str(self.0.to_string().as_str()));
+        if self.0.is_empty() || *self == Encoding::Identity {
+            return;
+        }
+        values.extend(headers::HeaderValue::from_str(self.to_string().as_str()));
+    }
+}
+
+/// Error type for Content-Encoding header value decoding
+#[cfg(feature = "http")]
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ContentEncodingDecodeError {
+    #[error("header value is not visible ASCII: {0}")]
+    InvalidHeaderValue(String),
+    #[error("encodings cannot be empty")]
+    EmptyEncodings,
+    #[error(transparent)]
+    Invalid(#[from] ContentEncodingError),
+}
+
+/// Appends the comma-separated encodings of a single header value, ignoring empty elements
+#[cfg(feature = "http")]
+fn decode_header_value_into(
+    header_value: &http::HeaderValue,
+    encodings: &mut Vec<Encoding>,
+) -> Result<(), ContentEncodingDecodeError> {
+    let value = header_value.to_str().map_err(|_| {
+        ContentEncodingDecodeError::InvalidHeaderValue(
+            String::from_utf8_lossy(header_value.as_bytes()).into_owned(),
+        )
+    })?;
+    for token in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        // Infallible
+        let encoding = Encoding::from_str(token).unwrap();
+        if matches!(encoding, Encoding::Wildcard) {
+            return Err(ContentEncodingError::WildcardNotAllowed.into());
+        }
+        encodings.push(encoding);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "http")]
+impl TryFrom<&http::HeaderValue> for ContentEncoding {
+    type Error = ContentEncodingDecodeError;
+
+    fn try_from(header_value: &http::HeaderValue) -> Result<Self, Self::Error> {
+        let mut encodings = Vec::new();
+        decode_header_value_into(header_value, &mut encodings)?;
+        if encodings.is_empty() {
+            return Err(ContentEncodingDecodeError::EmptyEncodings);
+        }
+        strip_redundant_identity(&mut encodings);
+        Ok(ContentEncoding(encodings))
+    }
+}
+
+#[cfg(feature = "http")]
+impl ContentEncoding {
+    /// Encodes the encodings into a single comma-joined header value, e.g. `gzip, br`,
+    /// without going through `headers::Header`.
+    ///
+    /// Unlike `Header::encode`, identity alone is emitted as `identity`, so callers
+    /// should omit the header for it themselves.
+    ///
+    /// Fails if a custom encoding contains characters not allowed in a header value,
+    /// which can only happen for encodings built with the lenient `Encoding::from_str`.
+    pub fn to_header_value(&self) -> Result<http::HeaderValue, http::header::InvalidHeaderValue> {
+        http::HeaderValue::try_from(self.to_string())
+    }
+
+    /// Decodes a single header value like `TryFrom<&HeaderValue>`, without going
+    /// through `headers::Header`.
+    pub fn from_header_value(
+        header_value: &http::HeaderValue,
+    ) -> Result<Self, ContentEncodingDecodeError> {
+        Self::try_from(header_value)
+    }
+}
+
+#[cfg(feature = "http_crates")]
+impl ContentEncoding {
+    /// Encodes the header as one header value per encoding, in the order they
+    /// were applied. This is equivalent to the comma-joined form emitted by
+    /// `Header::encode`, including emitting nothing for identity alone.
+    pub fn encode_multiline<E: Extend<headers::HeaderValue>>(&self, values: &mut E) {
+        if *self == Encoding::Identity {
+            return;
+        }
+        values.extend(
+            self.0
+                .iter()
+                .filter_map(|encoding| headers::HeaderValue::from_str(&encoding.to_string()).ok()),
+        );
+    }
+}
+
+#[cfg(all(test, feature = "http"))]
+mod http_tests {
+    use super::*;
+    use http::{HeaderMap, HeaderValue, header::CONTENT_ENCODING};
+
+    #[test]
+    fn test_header_value_round_trip() {
+        let stacked = ContentEncoding::from_encodings(vec![Encoding::Gzip, Encoding::Br]).unwrap();
+        let value = stacked.to_header_value().unwrap();
+        assert_eq!(value, "gzip, br");
+        assert_eq!(ContentEncoding::from_header_value(&value).unwrap(), stacked);
+
+        let mut map = HeaderMap::new();
+        let zstd = ContentEncoding::new(Encoding::Zstd).unwrap();
+        map.insert(CONTENT_ENCODING, zstd.to_header_value().unwrap());
+        let decoded = ContentEncoding::from_header_value(&map[CONTENT_ENCODING]).unwrap();
+        assert_eq!(decoded, Encoding::Zstd);
+
+        let identity = ContentEncoding::new(Encoding::Identity).unwrap();
+        assert_eq!(identity.to_header_value().unwrap(), "identity");
+    }
+
+    #[test]
+    fn test_to_header_value_rejects_invalid_characters() {
+        let encoding = "a\u{1}b".parse::<Encoding>().unwrap();
+        let content_encoding = ContentEncoding::new(encoding).unwrap();
+        assert!(content_encoding.to_header_value().is_err());
+    }
+
+    #[test]
+    fn test_from_header_value_errors() {
+        assert!(matches!(
+            ContentEncoding::from_header_value(&HeaderValue::from_static(" , ")),
+            Err(ContentEncodingDecodeError::EmptyEncodings)
+        ));
+        assert!(matches!(
+            ContentEncoding::from_header_value(&HeaderValue::from_static("gzip, *")),
+            Err(ContentEncodingDecodeError::Invalid(ContentEncodingError::WildcardNotAllowed))
+        ));
     }
 }
 
@@ -66,36 +418,386 @@ mod tests {
 
     #[test]
     fn test_decode_single_value() {
-        let header_values = vec![HeaderValue::from_str("gzip").unwrap()];
+        let header_values = [HeaderValue::from_str("gzip").unwrap()];
         let content_encoding = ContentEncoding::decode(&mut header_values.iter()).unwrap();
-        assert_eq!(content_encoding, ContentEncoding(Encoding::Gzip));
+        assert_eq!(content_encoding, ContentEncoding::new(Encoding::Gzip).unwrap());
+    }
+
+    #[test]
+    fn test_decode_identity_then_gzip_lines() {
+        let header_values = [
+            HeaderValue::from_static("identity"),
+            HeaderValue::from_static("gzip"),
+        ];
+        let content_encoding = ContentEncoding::decode(&mut header_values.iter()).unwrap();
+        assert_eq!(content_encoding.encodings(), &[Encoding::Gzip]);
+
+        let header_value = HeaderValue::from_static("identity, gzip");
+        let content_encoding = ContentEncoding::try_from(&header_value).unwrap();
+        assert_eq!(content_encoding.encodings(), &[Encoding::Gzip]);
+
+        let header_values = [HeaderValue::from_static("identity")];
+        let content_encoding = ContentEncoding::decode(&mut header_values.iter()).unwrap();
+        assert_eq!(content_encoding, ContentEncoding::identity());
+    }
+
+    #[test]
+    fn test_try_from_header_value() {
+        let header_value = HeaderValue::from_static("gzip, br");
+        assert_eq!(
+            ContentEncoding::try_from(&header_value).unwrap(),
+            ContentEncoding(vec![Encoding::Gzip, Encoding::Br])
+        );
+
+        let header_value = HeaderValue::from_static(" , ");
+        assert!(matches!(
+            ContentEncoding::try_from(&header_value),
+            Err(ContentEncodingDecodeError::EmptyEncodings)
+        ));
+
+        let header_value = HeaderValue::from_bytes(b"gzip\xff").unwrap();
+        assert!(matches!(
+            ContentEncoding::try_from(&header_value),
+            Err(ContentEncodingDecodeError::InvalidHeaderValue(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_single_value_with_two_encodings() {
+        // Regression: a comma-separated value used to become `Custom("gzip, br")`
+        let header_values = [HeaderValue::from_str("gzip, br").unwrap()];
+        let content_encoding = ContentEncoding::decode(&mut header_values.iter()).unwrap();
+        assert_eq!(content_encoding.encodings(), &[Encoding::Gzip, Encoding::Br]);
+        assert!(
+            !content_encoding
+                .encodings()
+                .iter()
+                .any(|enc| matches!(enc, Encoding::Custom(_)))
+        );
+    }
+
+    #[test]
+    fn test_typed_get_absent_is_none() {
+        // `typed_try_get` never calls `decode` for an absent header, so the error for
+        // zero values only surfaces when calling `decode` directly
+        let map = HeaderMap::new();
+        assert!(matches!(map.typed_try_get::<ContentEncoding>(), Ok(None)));
+        assert_eq!(map.typed_get::<ContentEncoding>(), None);
+
+        let mut map = HeaderMap::new();
+        map.insert(http::header::CONTENT_ENCODING, HeaderValue::from_static(""));
+        assert!(map.typed_try_get::<ContentEncoding>().is_err());
+    }
+
+    #[test]
+    fn test_decode_wildcard_rejected() {
+        let header_values = [HeaderValue::from_str("*").unwrap()];
+        assert!(ContentEncoding::decode(&mut header_values.iter()).is_err());
+
+        let header_value = HeaderValue::from_static("gzip, *");
+        assert!(matches!(
+            ContentEncoding::try_from(&header_value),
+            Err(ContentEncodingDecodeError::Invalid(
+                ContentEncodingError::WildcardNotAllowed
+            ))
+        ));
     }
 
     #[test]
     fn test_decode_multiple_identical_values() {
-        let header_values = vec![
+        let header_values = [
             HeaderValue::from_str("gzip").unwrap(),
             HeaderValue::from_str("gzip").unwrap(),
         ];
 
         let content_encoding = ContentEncoding::decode(&mut header_values.iter()).unwrap();
-        assert_eq!(content_encoding, ContentEncoding(Encoding::Gzip));
+        assert_eq!(
+            content_encoding,
+            ContentEncoding(vec![Encoding::Gzip, Encoding::Gzip])
+        );
     }
 
     #[test]
-    fn test_decode_conflicting_values() {
-        let header_values = vec![
+    fn test_decode_multiple_lines_stack() {
+        let header_values = [
             HeaderValue::from_str("gzip").unwrap(),
             HeaderValue::from_str("br").unwrap(),
         ];
+        let content_encoding = ContentEncoding::decode(&mut header_values.iter()).unwrap();
+        assert_eq!(content_encoding.encodings(), &[Encoding::Gzip, Encoding::Br]);
+    }
+
+    #[test]
+    fn test_decode_empty() {
+        let header_values = [HeaderValue::from_str("").unwrap()];
         assert!(ContentEncoding::decode(&mut header_values.iter()).is_err());
+        assert!(ContentEncoding::decode(&mut std::iter::empty()).is_err());
+    }
+
+    #[test]
+    fn test_round_trip_multiple_encodings() {
+        let content_encoding = ContentEncoding(vec![Encoding::Gzip, Encoding::Br]);
+
+        let mut map = HeaderMap::new();
+        map.typed_insert(content_encoding.clone());
+        assert_eq!(map.get_all(http::header::CONTENT_ENCODING).iter().count(), 1);
+        assert_eq!(map.get(http::header::CONTENT_ENCODING).unwrap(), "gzip, br");
+        assert_eq!(map.typed_get::<ContentEncoding>(), Some(content_encoding));
+    }
+
+    #[test]
+    fn test_round_trip_multiline() {
+        let content_encoding = ContentEncoding(vec![Encoding::Gzip, Encoding::Br]);
+
+        let mut values = Vec::new();
+        content_encoding.encode_multiline(&mut values);
+        assert_eq!(values, vec!["gzip", "br"]);
+
+        let mut map = HeaderMap::new();
+        for value in values {
+            map.append(http::header::CONTENT_ENCODING, value);
+        }
+        assert_eq!(map.typed_get::<ContentEncoding>(), Some(content_encoding));
     }
 
     #[test]
     fn test_encode() {
         let mut map = HeaderMap::new();
-        let content_encoding = ContentEncoding(Encoding::Gzip);
+        let content_encoding = ContentEncoding::new(Encoding::Gzip).unwrap();
         map.typed_insert(content_encoding);
         assert_eq!(map.get(http::header::CONTENT_ENCODING).unwrap(), "gzip");
     }
+
+    #[test]
+    fn test_encode_identity_emits_nothing() {
+        let mut map = HeaderMap::new();
+        map.typed_insert(ContentEncoding::identity());
+        assert!(map.get(http::header::CONTENT_ENCODING).is_none());
+
+        let mut values = Vec::new();
+        ContentEncoding::identity().encode_multiline(&mut values);
+        assert!(values.is_empty());
+
+        // Identity is only omitted on its own
+        map.typed_insert(ContentEncoding(vec![Encoding::Gzip, Encoding::Identity]));
+        assert_eq!(
+            map.get(http::header::CONTENT_ENCODING).unwrap(),
+            "gzip, identity"
+        );
+    }
+
+    #[test]
+    fn test_encode_stripped_emits_nothing() {
+        let mut content_encoding = ContentEncoding::new(Encoding::Gzip).unwrap();
+        content_encoding.strip_outermost();
+        let mut values = Vec::new();
+        content_encoding.encode(&mut values);
+        assert!(values.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod stack_tests {
+    use super::*;
+
+    #[test]
+    fn test_eq_encoding() {
+        let gzip = ContentEncoding::new(Encoding::Gzip).unwrap();
+        assert!(gzip == Encoding::Gzip);
+        assert!(gzip != Encoding::Br);
+
+        let stacked = ContentEncoding(vec![Encoding::Gzip, Encoding::Br]);
+        assert!(stacked != Encoding::Gzip);
+        assert!(stacked != Encoding::Br);
+
+        assert!(ContentEncoding(Vec::new()) != Encoding::Identity);
+    }
+
+    #[test]
+    fn test_ord_over_stack() {
+        use std::cmp::Ordering;
+        use std::collections::BTreeSet;
+
+        let gzip = ContentEncoding(vec![Encoding::Gzip]);
+        let gzip_br = ContentEncoding(vec![Encoding::Gzip, Encoding::Br]);
+        let br = ContentEncoding(vec![Encoding::Br]);
+        assert!(gzip < gzip_br);
+        assert!(gzip_br < br);
+
+        let custom_upper = ContentEncoding(vec![Encoding::Custom("X-Foo".to_string())]);
+        let custom_lower = ContentEncoding(vec![Encoding::Custom("x-foo".to_string())]);
+        assert_eq!(custom_upper.cmp(&custom_lower), Ordering::Equal);
+
+        let set: BTreeSet<_> = [br.clone(), gzip_br.clone(), gzip.clone(), br.clone()].into();
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), [gzip, gzip_br, br]);
+    }
+
+    #[test]
+    fn test_try_new_for() {
+        let accept = AcceptEncoding::parse("gzip, br;q=0, *;q=0.5").unwrap();
+        let gzip = ContentEncoding::try_new_for(Encoding::Gzip, &accept).unwrap();
+        assert_eq!(gzip, Encoding::Gzip);
+        assert!(ContentEncoding::try_new_for(Encoding::Zstd, &accept).is_ok());
+        assert!(matches!(
+            ContentEncoding::try_new_for(Encoding::Br, &accept),
+            Err(ContentEncodingError::RejectedByClient(Encoding::Br))
+        ));
+
+        let accept = AcceptEncoding::parse("gzip, *;q=0").unwrap();
+        assert!(matches!(
+            ContentEncoding::try_new_for(Encoding::Zstd, &accept),
+            Err(ContentEncodingError::RejectedByClient(Encoding::Zstd))
+        ));
+    }
+
+    #[test]
+    fn test_push_layer() {
+        let mut stack = ContentEncoding::new(Encoding::Gzip).unwrap();
+        stack.push_layer(Encoding::Br).unwrap();
+        assert_eq!(stack.encodings(), &[Encoding::Gzip, Encoding::Br]);
+        assert_eq!(stack.encoding(), Some(&Encoding::Br));
+
+        assert!(matches!(
+            stack.push_layer(Encoding::Identity),
+            Err(ContentEncodingError::IdentityInStack)
+        ));
+        assert!(matches!(
+            stack.push_layer(Encoding::Wildcard),
+            Err(ContentEncodingError::WildcardNotAllowed)
+        ));
+        assert_eq!(stack.encodings(), &[Encoding::Gzip, Encoding::Br]);
+
+        let mut identity = ContentEncoding::identity();
+        identity.push_layer(Encoding::Gzip).unwrap();
+        assert_eq!(identity.encodings(), &[Encoding::Gzip]);
+    }
+
+    #[test]
+    fn test_normalized() {
+        let redundant = ContentEncoding(vec![Encoding::Identity, Encoding::Gzip]);
+        let gzip = ContentEncoding::new(Encoding::Gzip).unwrap();
+        assert_ne!(redundant, gzip);
+        assert_eq!(redundant.normalized(), gzip.normalized());
+        assert_eq!(redundant.normalized(), Encoding::Gzip);
+
+        let identity = ContentEncoding(vec![Encoding::Identity, Encoding::Identity]);
+        assert_eq!(identity.normalized(), ContentEncoding::identity());
+        assert!(ContentEncoding(Vec::new()).normalized().is_empty());
+    }
+
+    #[test]
+    fn test_as_str_list() {
+        let single = ContentEncoding::new(Encoding::Gzip).unwrap();
+        assert_eq!(single.as_str_list(), ["gzip"]);
+
+        let stacked = ContentEncoding(vec![
+            Encoding::Gzip,
+            Encoding::Custom("x-foo".to_string()),
+        ]);
+        assert_eq!(stacked.as_str_list(), ["gzip", "x-foo"]);
+    }
+
+    #[test]
+    fn test_from_negotiation_result() {
+        let selected: Option<ContentEncoding> =
+            NegotiationResult::Selected(Encoding::Br).into();
+        assert_eq!(selected, Some(ContentEncoding(vec![Encoding::Br])));
+
+        let identity: Option<ContentEncoding> = NegotiationResult::Identity.into();
+        assert_eq!(identity, None);
+
+        let not_acceptable: Option<ContentEncoding> = NegotiationResult::NotAcceptable.into();
+        assert_eq!(not_acceptable, None);
+    }
+
+    #[test]
+    fn test_from_encodings() {
+        let stacked = ContentEncoding::from_encodings(vec![Encoding::Gzip, Encoding::Br]).unwrap();
+        assert_eq!(stacked.encodings(), &[Encoding::Gzip, Encoding::Br]);
+        assert_eq!(
+            ContentEncoding::from_encodings(vec![Encoding::Identity]).unwrap(),
+            ContentEncoding::identity()
+        );
+
+        assert!(matches!(
+            ContentEncoding::from_encodings(vec![Encoding::Gzip, Encoding::Identity]),
+            Err(ContentEncodingError::IdentityInStack)
+        ));
+        assert!(matches!(
+            ContentEncoding::from_encodings(vec![Encoding::Gzip, Encoding::Wildcard]),
+            Err(ContentEncodingError::WildcardNotAllowed)
+        ));
+        assert!(matches!(
+            ContentEncoding::from_encodings(Vec::new()),
+            Err(ContentEncodingError::EmptyEncodings)
+        ));
+    }
+
+    #[test]
+    fn test_acceptable_layers() {
+        let stacked = ContentEncoding(vec![Encoding::Gzip, Encoding::Br]);
+        let accept = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0)]).unwrap();
+
+        let layers: Vec<_> = stacked.acceptable_layers(&accept).collect();
+        assert_eq!(layers, vec![(&Encoding::Gzip, true), (&Encoding::Br, false)]);
+    }
+
+    #[test]
+    fn test_new_rejects_wildcard() {
+        assert!(matches!(
+            ContentEncoding::new(Encoding::Wildcard),
+            Err(ContentEncodingError::WildcardNotAllowed)
+        ));
+    }
+
+    #[test]
+    fn test_strip_outermost() {
+        let mut content_encoding = ContentEncoding(vec![Encoding::Gzip, Encoding::Br]);
+        assert_eq!(content_encoding.to_string(), "gzip, br");
+        assert_eq!(content_encoding.encoding(), Some(&Encoding::Br));
+
+        assert_eq!(content_encoding.strip_outermost(), Some(Encoding::Br));
+        assert_eq!(content_encoding.encodings(), &[Encoding::Gzip]);
+        assert_eq!(content_encoding.to_string(), "gzip");
+
+        assert_eq!(content_encoding.strip_outermost(), Some(Encoding::Gzip));
+        assert!(content_encoding.is_empty());
+        assert_eq!(content_encoding.encoding(), None);
+        assert_eq!(content_encoding.to_string(), "");
+
+        assert_eq!(content_encoding.strip_outermost(), None);
+    }
+
+    #[test]
+    fn test_strip_all() {
+        let mut content_encoding = ContentEncoding(vec![Encoding::Gzip, Encoding::Br]);
+        assert_eq!(content_encoding.strip_all(), vec![Encoding::Br, Encoding::Gzip]);
+        assert!(content_encoding.is_empty());
+        assert!(content_encoding.strip_all().is_empty());
+    }
+
+    #[test]
+    fn test_looks_like() {
+        let gzip = ContentEncoding::new(Encoding::Gzip).unwrap();
+        assert!(gzip.looks_like(&[0x1f, 0x8b, 0x08, 0x00]));
+        assert!(!gzip.looks_like(&[0x28, 0xb5, 0x2f, 0xfd]));
+        assert!(!gzip.looks_like(b"{\"json\": true}"));
+
+        let zstd = ContentEncoding::new(Encoding::Zstd).unwrap();
+        assert!(zstd.looks_like(&[0x28, 0xb5, 0x2f, 0xfd, 0x24, 0x00]));
+        assert!(!zstd.looks_like(&[0x1f, 0x8b, 0x08, 0x00]));
+
+        let zlib = ContentEncoding::new(Encoding::Zlib).unwrap();
+        assert!(zlib.looks_like(&[0x78, 0x9c]));
+        assert!(!zlib.looks_like(&[0x78, 0x9d]));
+
+        // Only the outermost layer is checked
+        let stack = ContentEncoding(vec![Encoding::Gzip, Encoding::Zstd]);
+        assert!(stack.looks_like(&[0x28, 0xb5, 0x2f, 0xfd]));
+
+        // No magic number or not enough bytes to tell
+        assert!(ContentEncoding::new(Encoding::Br).unwrap().looks_like(b"anything"));
+        assert!(ContentEncoding::new(Encoding::Identity).unwrap().looks_like(b"anything"));
+        assert!(gzip.looks_like(&[0x1f]));
+    }
 }
@@ -1,5 +1,8 @@
+use std::cmp::Ordering;
 use std::convert::Infallible;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use thiserror::Error;
 
 const ENC_GZIP: &str = "gzip";
 const ENC_DEFLATE: &str = "deflate";
@@ -15,13 +18,77 @@ const ENC_LZ4: &str = "lz4";
 const ENC_ZLIB: &str = "zlib";
 const ENC_WILDCARD: &str = "*";
 
+/// Returns true if `s` is a valid HTTP token (RFC 9110, section 5.6.2)
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(is_tchar)
+}
+
+/// Returns true if `b` is a valid HTTP token character
+fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// Error type for constructing `Encoding`
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum EncodingError {
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    #[error("token is {0} bytes long, the limit is {max}", max = Encoding::MAX_TOKEN_LEN)]
+    TokenTooLong(usize),
+}
+
 /// Quality value type used for encoding preferences
 pub type QualityValue = f32;
 
+/// Stable category of an [`Encoding`]
+///
+/// Unlike `Encoding` itself, this enum is not expected to grow, so matching on it
+/// exhaustively is forward-compatible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncodingKind {
+    /// A registered content coding known to this crate, e.g. `gzip` or `br`
+    WellKnown,
+    /// The `*` wildcard
+    Wildcard,
+    /// Any other content coding token
+    Custom,
+}
+
+/// Compression algorithm family of an [`Encoding`], see [`Encoding::family`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EncodingFamily {
+    /// DEFLATE based: `gzip`, `deflate` and `zlib`
+    Deflate,
+    /// `br`
+    Brotli,
+    /// `zstd`
+    Zstd,
+    /// LZW based `compress`
+    Legacy,
+    /// Other Lempel-Ziv and block sorting compressors: `lz4`, `lzma`, `xz`,
+    /// `bzip2` and `snappy`
+    Lz,
+    /// `identity`, i.e. no compression
+    Identity,
+    /// The wildcard and custom encodings
+    Other,
+}
+
 /// Represents supported HTTP content encodings
 ///
 /// Used to specify compression and encoding schemes for HTTP message bodies.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// This enum is `#[non_exhaustive]` as new standard encodings may be added over
+/// time. Matches on it outside this crate need a `_ =>` arm; use [`Encoding::kind`]
+/// to match on a stable category instead.
+///
+/// `Custom` encodings keep their original casing for display, but compare, hash
+/// and order case-insensitively, so `X-Foo` and `x-foo` negotiate as the same
+/// encoding.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Encoding {
     Gzip,
     Deflate,
@@ -39,47 +106,641 @@ pub enum Encoding {
     Custom(String),
 }
 
+/// Default server-side ranking used when the client expresses no preference
+static DEFAULT_PRIORITY: [Encoding; 5] = [
+    Encoding::Zstd,
+    Encoding::Br,
+    Encoding::Gzip,
+    Encoding::Deflate,
+    Encoding::Identity,
+];
+
+impl Encoding {
+    /// Returns a reasonable modern ranking of encodings, most preferred first.
+    ///
+    /// zstd compresses about as well as br at a fraction of the CPU cost, br
+    /// yields the smallest text payloads, gzip is understood by every client, and
+    /// deflate ranks below gzip because implementations disagree on whether it
+    /// means raw deflate or zlib. Identity comes last as it does not compress at all.
+    pub fn default_priority() -> &'static [Encoding] {
+        &DEFAULT_PRIORITY
+    }
+
+    /// Creates a custom encoding, validating that `token` is a legal HTTP token.
+    ///
+    /// Unlike the infallible `from_str`, whitespace, control characters and
+    /// separators like `;` are rejected. Tokens of known encodings yield their
+    /// standard variant, e.g. `gzip` yields `Encoding::Gzip`.
+    pub fn custom(token: impl Into<String>) -> Result<Encoding, EncodingError> {
+        Encoding::try_from(token.into().as_str())
+    }
+
+    /// Longest token accepted by `from_bytes`.
+    pub const MAX_TOKEN_LEN: usize = 64;
+
+    /// Creates an encoding from raw header bytes, e.g. a slice of a `HeaderValue`.
+    ///
+    /// The bytes must form a legal HTTP token of at most `MAX_TOKEN_LEN` bytes, so a
+    /// `Custom` encoding built here never holds illegal characters or unbounded data.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Encoding, EncodingError> {
+        if bytes.len() > Self::MAX_TOKEN_LEN {
+            return Err(EncodingError::TokenTooLong(bytes.len()));
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(token) => Encoding::try_from(token),
+            Err(_) => Err(EncodingError::InvalidToken(String::from_utf8_lossy(bytes).into_owned())),
+        }
+    }
+
+    /// Parses `s` like `from_str`, also returning whether `s` was an alias such as
+    /// `x-gzip` or `brotli` which got normalized to its registered token.
+    ///
+    /// A mere difference in case, e.g. `GZIP`, does not count as an alias.
+    pub fn from_str_with_alias_info(s: &str) -> (Encoding, bool) {
+        // Infallible
+        let encoding = Encoding::from_str(s).unwrap();
+        let alias = !matches!(encoding, Encoding::Custom(_))
+            && !encoding.as_str().eq_ignore_ascii_case(s);
+        (encoding, alias)
+    }
+
+    /// Returns the token of this encoding without allocating.
+    ///
+    /// Standard variants return a `&'static str`, custom encodings borrow their token.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Encoding::Gzip => ENC_GZIP,
+            Encoding::Deflate => ENC_DEFLATE,
+            Encoding::Compress => ENC_COMPRESS,
+            Encoding::Identity => ENC_IDENTITY,
+            Encoding::Br => ENC_BR,
+            Encoding::Zstd => ENC_ZSTD,
+            Encoding::Snappy => ENC_SNAPPY,
+            Encoding::Xz => ENC_XZ,
+            Encoding::Lzma => ENC_LZMA,
+            Encoding::Bzip2 => ENC_BZIP2,
+            Encoding::Lz4 => ENC_LZ4,
+            Encoding::Zlib => ENC_ZLIB,
+            Encoding::Wildcard => ENC_WILDCARD,
+            Encoding::Custom(s) => s,
+        }
+    }
+
+    /// Returns true if this is the identity encoding, i.e. no encoding at all
+    #[inline]
+    pub fn is_identity(&self) -> bool {
+        matches!(self, Encoding::Identity)
+    }
+
+    /// Returns true if this encoding, used as a pattern, matches `other`.
+    ///
+    /// `Wildcard` matches any encoding, every other encoding only matches itself.
+    /// Matching is not symmetric: `Wildcard.encoding_matches(&Encoding::Gzip)` is
+    /// true, while `Encoding::Gzip.encoding_matches(&Encoding::Wildcard)` is false.
+    /// `PartialEq` stays strict and never treats the wildcard specially.
+    pub fn encoding_matches(&self, other: &Encoding) -> bool {
+        matches!(self, Encoding::Wildcard) || self == other
+    }
+
+    /// Returns the stable category of this encoding
+    pub fn kind(&self) -> EncodingKind {
+        match self {
+            Encoding::Wildcard => EncodingKind::Wildcard,
+            Encoding::Custom(_) => EncodingKind::Custom,
+            _ => EncodingKind::WellKnown,
+        }
+    }
+
+    /// Returns the compression algorithm family of this encoding, e.g. for
+    /// aggregating metrics.
+    ///
+    /// See [`EncodingFamily`] for the mapping.
+    pub fn family(&self) -> EncodingFamily {
+        match self {
+            Encoding::Gzip | Encoding::Deflate | Encoding::Zlib => EncodingFamily::Deflate,
+            Encoding::Br => EncodingFamily::Brotli,
+            Encoding::Zstd => EncodingFamily::Zstd,
+            Encoding::Compress => EncodingFamily::Legacy,
+            Encoding::Lz4 | Encoding::Lzma | Encoding::Xz | Encoding::Bzip2 | Encoding::Snappy => {
+                EncodingFamily::Lz
+            }
+            Encoding::Identity => EncodingFamily::Identity,
+            Encoding::Wildcard | Encoding::Custom(_) => EncodingFamily::Other,
+        }
+    }
+
+    /// Returns true if this encoding suits streaming responses.
+    ///
+    /// Streamable encodings can flush everything written so far at any point, so a
+    /// chunked response reaches the client without buffering: identity, gzip,
+    /// deflate, zlib, br, zstd, lz4, snappy and xz. `compress` and `lzma` cannot
+    /// flush mid-stream and bzip2 holds back output until a block of up to 900 kB
+    /// is full. Wildcard and custom encodings are unknown and yield false.
+    pub fn is_stream_compressible(&self) -> bool {
+        matches!(
+            self,
+            Encoding::Identity
+                | Encoding::Gzip
+                | Encoding::Deflate
+                | Encoding::Zlib
+                | Encoding::Br
+                | Encoding::Zstd
+                | Encoding::Lz4
+                | Encoding::Snappy
+                | Encoding::Xz
+        )
+    }
+
+    /// Returns the MIME type of a standalone file compressed with this encoding,
+    /// e.g. `application/gzip` for gzip.
+    ///
+    /// Useful as Content-Type when serving pre-compressed files as-is. Returns `None`
+    /// for encodings without a common file format, identity, wildcard and custom
+    /// encodings.
+    pub fn file_mime_type(&self) -> Option<&'static str> {
+        match self {
+            Encoding::Gzip => Some("application/gzip"),
+            Encoding::Compress => Some("application/x-compress"),
+            Encoding::Br => Some("application/x-brotli"),
+            Encoding::Zstd => Some("application/zstd"),
+            Encoding::Xz => Some("application/x-xz"),
+            Encoding::Lzma => Some("application/x-lzma"),
+            Encoding::Bzip2 => Some("application/x-bzip2"),
+            Encoding::Lz4 => Some("application/x-lz4"),
+            Encoding::Zlib => Some("application/zlib"),
+            _ => None,
+        }
+    }
+
+    /// Returns the commonly recommended default compression level, as advisory
+    /// metadata for servers tuning their compressors.
+    ///
+    /// These are the defaults of the reference implementations: zlib level 6 for
+    /// gzip, deflate and zlib, `ZSTD_CLEVEL_DEFAULT` 3 for zstd, xz preset 6 for xz
+    /// and lzma, block size 9 for bzip2 and level 1 for lz4. For br the reference
+    /// default of 11 is far too slow for on-the-fly compression, so the level 5
+    /// commonly recommended for dynamic content is returned instead. Returns `None`
+    /// for encodings without levels (compress, snappy), identity, wildcard and
+    /// custom encodings.
+    pub fn default_level(&self) -> Option<u32> {
+        match self {
+            Encoding::Gzip | Encoding::Deflate | Encoding::Zlib => Some(6),
+            Encoding::Br => Some(5),
+            Encoding::Zstd => Some(3),
+            Encoding::Xz | Encoding::Lzma => Some(6),
+            Encoding::Bzip2 => Some(9),
+            Encoding::Lz4 => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Returns a rough typical compressed-to-original size ratio, e.g. 0.3 for gzip.
+    ///
+    /// This is a heuristic for logging and capacity estimates only: the values are
+    /// typical for text payloads like HTML, CSS, JS and JSON at default levels and
+    /// vary widely with the actual content. Returns `None` for encodings without a
+    /// meaningful estimate, identity, wildcard and custom encodings.
+    pub fn typical_ratio_hint(&self) -> Option<f32> {
+        match self {
+            Encoding::Gzip | Encoding::Deflate | Encoding::Zlib => Some(0.3),
+            Encoding::Br => Some(0.25),
+            Encoding::Zstd => Some(0.28),
+            Encoding::Compress => Some(0.45),
+            Encoding::Xz | Encoding::Lzma => Some(0.25),
+            Encoding::Bzip2 => Some(0.27),
+            Encoding::Lz4 | Encoding::Snappy => Some(0.45),
+            _ => None,
+        }
+    }
+
+    /// Returns the declaration order index of the variant
+    fn variant_index(&self) -> u8 {
+        match self {
+            Encoding::Gzip => 0,
+            Encoding::Deflate => 1,
+            Encoding::Compress => 2,
+            Encoding::Identity => 3,
+            Encoding::Br => 4,
+            Encoding::Zstd => 5,
+            Encoding::Snappy => 6,
+            Encoding::Xz => 7,
+            Encoding::Lzma => 8,
+            Encoding::Bzip2 => 9,
+            Encoding::Lz4 => 10,
+            Encoding::Zlib => 11,
+            Encoding::Wildcard => 12,
+            Encoding::Custom(_) => 13,
+        }
+    }
+}
+
+/// Builds an array of standard encodings from their header tokens, without any
+/// runtime parsing.
+///
+/// The result can initialize a `const` or `static`, so servers can declare their
+/// supported encodings once and use them as `&[Encoding]`. Only standard encodings
+/// are supported, as `Encoding::Custom` cannot be constructed in a const context;
+/// unknown tokens fail to compile. Use `star` for the wildcard.
+///
+/// ```
+/// use http_encoding_headers::{Encoding, supported_encodings};
+///
+/// const SUPPORTED: [Encoding; 3] = supported_encodings!(zstd, br, gzip);
+/// assert_eq!(SUPPORTED, [Encoding::Zstd, Encoding::Br, Encoding::Gzip]);
+/// ```
+#[macro_export]
+macro_rules! supported_encodings {
+    ($($token:ident),* $(,)?) => {
+        [$($crate::__encoding_from_token!($token)),*]
+    };
+}
+
+/// Maps a single header token to its `Encoding` variant for `supported_encodings!`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __encoding_from_token {
+    (gzip) => { $crate::Encoding::Gzip };
+    (deflate) => { $crate::Encoding::Deflate };
+    (compress) => { $crate::Encoding::Compress };
+    (identity) => { $crate::Encoding::Identity };
+    (br) => { $crate::Encoding::Br };
+    (zstd) => { $crate::Encoding::Zstd };
+    (snappy) => { $crate::Encoding::Snappy };
+    (xz) => { $crate::Encoding::Xz };
+    (lzma) => { $crate::Encoding::Lzma };
+    (bzip2) => { $crate::Encoding::Bzip2 };
+    (lz4) => { $crate::Encoding::Lz4 };
+    (zlib) => { $crate::Encoding::Zlib };
+    (star) => { $crate::Encoding::Wildcard };
+    ($other:ident) => {
+        compile_error!(concat!("not a standard encoding: ", stringify!($other)))
+    };
+}
+
+/// Parses a content coding token case-insensitively; unknown tokens become
+/// `Encoding::Custom` with their original casing.
+///
+/// The aliases `x-gzip` and `x-compress`, which RFC 9110 declares equivalent to
+/// `gzip` and `compress`, and the non-standard `brotli` and `zstandard` are
+/// recognized as well, while `Display` keeps emitting the registered tokens.
 impl FromStr for Encoding {
     type Err = Infallible;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let lowercase_s = s.to_lowercase();
-        match lowercase_s.as_str() {
-            ENC_GZIP => Ok(Encoding::Gzip),
-            ENC_DEFLATE => Ok(Encoding::Deflate),
-            ENC_COMPRESS => Ok(Encoding::Compress),
-            ENC_IDENTITY => Ok(Encoding::Identity),
-            ENC_BR => Ok(Encoding::Br),
-            ENC_ZSTD => Ok(Encoding::Zstd),
-            ENC_SNAPPY => Ok(Encoding::Snappy),
-            ENC_XZ => Ok(Encoding::Xz),
-            ENC_LZMA => Ok(Encoding::Lzma),
-            ENC_BZIP2 => Ok(Encoding::Bzip2),
-            ENC_LZ4 => Ok(Encoding::Lz4),
-            ENC_ZLIB => Ok(Encoding::Zlib),
-            ENC_WILDCARD => Ok(Encoding::Wildcard),
-            _ => Ok(Encoding::Custom(lowercase_s)),
+        // Compares case-insensitively without allocating a lowercase copy
+        let known = [
+            (ENC_GZIP, Encoding::Gzip),
+            (ENC_DEFLATE, Encoding::Deflate),
+            (ENC_COMPRESS, Encoding::Compress),
+            (ENC_IDENTITY, Encoding::Identity),
+            (ENC_BR, Encoding::Br),
+            (ENC_ZSTD, Encoding::Zstd),
+            (ENC_SNAPPY, Encoding::Snappy),
+            (ENC_XZ, Encoding::Xz),
+            (ENC_LZMA, Encoding::Lzma),
+            (ENC_BZIP2, Encoding::Bzip2),
+            (ENC_LZ4, Encoding::Lz4),
+            (ENC_ZLIB, Encoding::Zlib),
+            (ENC_WILDCARD, Encoding::Wildcard),
+            // Legacy aliases from RFC 9110, section 8.4.1
+            ("x-gzip", Encoding::Gzip),
+            ("x-compress", Encoding::Compress),
+            // Convenience aliases some clients and tools write out
+            ("brotli", Encoding::Br),
+            ("zstandard", Encoding::Zstd),
+        ];
+        Ok(known
+            .into_iter()
+            .find(|(token, _)| token.eq_ignore_ascii_case(s))
+            .map_or_else(|| Encoding::Custom(s.to_string()), |(_, encoding)| encoding))
+    }
+}
+
+/// Strict counterpart of `FromStr`, rejecting strings which are not valid HTTP
+/// tokens like `Encoding::custom`
+impl TryFrom<&str> for Encoding {
+    type Error = EncodingError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        if !is_token(s) {
+            return Err(EncodingError::InvalidToken(s.to_string()));
+        }
+        // Infallible
+        Ok(Encoding::from_str(s).unwrap())
+    }
+}
+
+impl PartialEq for Encoding {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Encoding::Custom(a), Encoding::Custom(b)) => a.eq_ignore_ascii_case(b),
+            _ => self.variant_index() == other.variant_index(),
+        }
+    }
+}
+
+impl Eq for Encoding {}
+
+impl Hash for Encoding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.variant_index().hash(state);
+        if let Encoding::Custom(s) = self {
+            for b in s.bytes() {
+                state.write_u8(b.to_ascii_lowercase());
+            }
+            // Terminate like `str` does to avoid prefix collisions
+            state.write_u8(0xff);
+        }
+    }
+}
+
+impl PartialOrd for Encoding {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Encoding {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Encoding::Custom(a), Encoding::Custom(b)) => a
+                .bytes()
+                .map(|b| b.to_ascii_lowercase())
+                .cmp(b.bytes().map(|b| b.to_ascii_lowercase())),
+            _ => self.variant_index().cmp(&other.variant_index()),
         }
     }
 }
 
 impl std::fmt::Display for Encoding {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Encoding::Gzip => f.write_str(ENC_GZIP),
-            Encoding::Deflate => f.write_str(ENC_DEFLATE),
-            Encoding::Compress => f.write_str(ENC_COMPRESS),
-            Encoding::Identity => f.write_str(ENC_IDENTITY),
-            Encoding::Br => f.write_str(ENC_BR),
-            Encoding::Zstd => f.write_str(ENC_ZSTD),
-            Encoding::Snappy => f.write_str(ENC_SNAPPY),
-            Encoding::Xz => f.write_str(ENC_XZ),
-            Encoding::Lzma => f.write_str(ENC_LZMA),
-            Encoding::Bzip2 => f.write_str(ENC_BZIP2),
-            Encoding::Lz4 => f.write_str(ENC_LZ4),
-            Encoding::Zlib => f.write_str(ENC_ZLIB),
-            Encoding::Wildcard => f.write_str(ENC_WILDCARD),
-            Encoding::Custom(s) => f.write_str(s),
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_priority() {
+        let priority = Encoding::default_priority();
+        let rank = |e: &Encoding| priority.iter().position(|p| p == e).unwrap();
+        assert!(rank(&Encoding::Zstd) < rank(&Encoding::Br));
+        assert!(rank(&Encoding::Br) < rank(&Encoding::Gzip));
+        assert!(rank(&Encoding::Gzip) < rank(&Encoding::Deflate));
+        assert_eq!(priority.last(), Some(&Encoding::Identity));
+    }
+
+    #[test]
+    fn test_is_token() {
+        assert!(is_token("gzip"));
+        assert!(is_token("x-custom_1.0"));
+        assert!(is_token("*"));
+        assert!(!is_token(""));
+        assert!(!is_token("\"gzip\""));
+        assert!(!is_token("x foo"));
+        assert!(!is_token("x;y"));
+    }
+
+    #[test]
+    fn test_encoding_matches() {
+        assert!(Encoding::Gzip.encoding_matches(&Encoding::Gzip));
+        assert!(!Encoding::Gzip.encoding_matches(&Encoding::Br));
+
+        // Wildcard as the pattern matches everything
+        assert!(Encoding::Wildcard.encoding_matches(&Encoding::Gzip));
+        assert!(Encoding::Wildcard.encoding_matches(&Encoding::Identity));
+        assert!(Encoding::Wildcard.encoding_matches(&Encoding::Custom("x-foo".to_string())));
+        assert!(Encoding::Wildcard.encoding_matches(&Encoding::Wildcard));
+
+        // A concrete pattern does not match the wildcard implicitly
+        assert!(!Encoding::Gzip.encoding_matches(&Encoding::Wildcard));
+        assert_ne!(Encoding::Wildcard, Encoding::Gzip);
+    }
+
+    #[test]
+    fn test_custom_compares_case_insensitively() {
+        use std::collections::HashSet;
+
+        let upper = Encoding::from_str("X-Foo").unwrap();
+        let lower = Encoding::from_str("x-foo").unwrap();
+        assert_eq!(Encoding::Custom("X-Foo".to_string()), Encoding::Custom("x-foo".to_string()));
+        assert_eq!(upper, lower);
+        assert_eq!(upper.cmp(&lower), Ordering::Equal);
+        assert_ne!(upper, Encoding::Custom("x-bar".to_string()));
+
+        // Display preserves the original casing
+        assert_eq!(upper.to_string(), "X-Foo");
+        assert_eq!(lower.to_string(), "x-foo");
+
+        let set: HashSet<Encoding> = [upper, lower].into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        // Known encodings still parse case-insensitively
+        assert_eq!(Encoding::from_str("GZip").unwrap(), Encoding::Gzip);
+        assert!(Encoding::Gzip < Encoding::Custom("a".to_string()));
+    }
+
+    #[test]
+    fn test_file_mime_type() {
+        assert_eq!(Encoding::Gzip.file_mime_type(), Some("application/gzip"));
+        assert_eq!(Encoding::Zstd.file_mime_type(), Some("application/zstd"));
+        assert_eq!(Encoding::Br.file_mime_type(), Some("application/x-brotli"));
+        assert_eq!(Encoding::Identity.file_mime_type(), None);
+        assert_eq!(Encoding::Wildcard.file_mime_type(), None);
+        assert_eq!(
+            Encoding::Custom("x-custom".to_string()).file_mime_type(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_default_level() {
+        assert_eq!(Encoding::Gzip.default_level(), Some(6));
+        assert_eq!(Encoding::Deflate.default_level(), Some(6));
+        assert_eq!(Encoding::Zstd.default_level(), Some(3));
+        assert_eq!(Encoding::Br.default_level(), Some(5));
+        assert_eq!(Encoding::Compress.default_level(), None);
+        assert_eq!(Encoding::Identity.default_level(), None);
+        assert_eq!(Encoding::Wildcard.default_level(), None);
+        assert_eq!(Encoding::Custom("x-custom".to_string()).default_level(), None);
+    }
+
+    #[test]
+    fn test_typical_ratio_hint() {
+        let compressing = [
+            Encoding::Gzip,
+            Encoding::Deflate,
+            Encoding::Compress,
+            Encoding::Br,
+            Encoding::Zstd,
+            Encoding::Snappy,
+            Encoding::Xz,
+            Encoding::Lzma,
+            Encoding::Bzip2,
+            Encoding::Lz4,
+            Encoding::Zlib,
+        ];
+        for encoding in compressing {
+            let ratio = encoding.typical_ratio_hint().unwrap();
+            assert!(ratio > 0.0 && ratio < 1.0, "{encoding}: {ratio}");
+        }
+        assert_eq!(Encoding::Identity.typical_ratio_hint(), None);
+        assert_eq!(Encoding::Wildcard.typical_ratio_hint(), None);
+    }
+
+    #[test]
+    fn test_supported_encodings_macro() {
+        const SUPPORTED: [Encoding; 3] = supported_encodings!(gzip, br, zstd);
+        static ALL: &[Encoding] = &supported_encodings!(
+            gzip, deflate, compress, identity, br, zstd, snappy, xz, lzma, bzip2, lz4, zlib, star,
+        );
+
+        assert_eq!(
+            SUPPORTED.to_vec(),
+            vec![Encoding::Gzip, Encoding::Br, Encoding::Zstd]
+        );
+        for encoding in ALL {
+            assert_eq!(&Encoding::from_str(&encoding.to_string()).unwrap(), encoding);
         }
     }
+
+    #[test]
+    fn test_from_str_aliases() {
+        assert_eq!("brotli".parse::<Encoding>(), Ok(Encoding::Br));
+        assert_eq!("Brotli".parse::<Encoding>(), Ok(Encoding::Br));
+        assert_eq!("zstandard".parse::<Encoding>(), Ok(Encoding::Zstd));
+        assert_eq!(Encoding::from_str("brotli").unwrap().to_string(), "br");
+        assert_eq!(Encoding::from_str("zstandard").unwrap().to_string(), "zstd");
+        assert_eq!("x-gzip".parse::<Encoding>(), Ok(Encoding::Gzip));
+        assert_eq!("X-Compress".parse::<Encoding>(), Ok(Encoding::Compress));
+    }
+
+    #[test]
+    fn test_from_str_with_alias_info() {
+        assert_eq!(Encoding::from_str_with_alias_info("x-gzip"), (Encoding::Gzip, true));
+        assert_eq!(Encoding::from_str_with_alias_info("brotli"), (Encoding::Br, true));
+        assert_eq!(Encoding::from_str_with_alias_info("gzip"), (Encoding::Gzip, false));
+        assert_eq!(Encoding::from_str_with_alias_info("GZIP"), (Encoding::Gzip, false));
+        assert_eq!(
+            Encoding::from_str_with_alias_info("x-foo"),
+            (Encoding::Custom("x-foo".to_string()), false)
+        );
+    }
+
+    #[test]
+    fn test_custom() {
+        assert_eq!(
+            Encoding::custom("x-foo").unwrap(),
+            Encoding::Custom("x-foo".to_string())
+        );
+        assert_eq!(Encoding::custom("GZIP").unwrap(), Encoding::Gzip);
+
+        for invalid in ["x foo", "x;y", "", "x\ty", "\"x\""] {
+            let result = Encoding::custom(invalid);
+            assert!(
+                matches!(result, Err(EncodingError::InvalidToken(ref t)) if t == invalid),
+                "{invalid:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_as_str() {
+        assert_eq!(Encoding::Gzip.as_str(), "gzip");
+        assert_eq!(Encoding::Wildcard.as_str(), "*");
+
+        // Custom tokens are borrowed, not copied
+        let custom = Encoding::Custom("x-foo".to_string());
+        let Encoding::Custom(ref token) = custom else {
+            unreachable!()
+        };
+        assert_eq!(custom.as_str().as_ptr(), token.as_ptr());
+        assert_eq!(custom.as_str(), custom.to_string());
+    }
+
+    #[test]
+    fn test_family() {
+        assert_eq!(Encoding::Gzip.family(), EncodingFamily::Deflate);
+        assert_eq!(Encoding::Deflate.family(), Encoding::Gzip.family());
+        assert_eq!(Encoding::Zlib.family(), Encoding::Gzip.family());
+        assert_ne!(Encoding::Br.family(), Encoding::Gzip.family());
+        assert_eq!(Encoding::Br.family(), EncodingFamily::Brotli);
+        assert_eq!(Encoding::Xz.family(), EncodingFamily::Lz);
+        assert_eq!(Encoding::Compress.family(), EncodingFamily::Legacy);
+        assert_eq!(
+            Encoding::Custom("x-custom".to_string()).family(),
+            EncodingFamily::Other
+        );
+    }
+
+    #[test]
+    fn test_try_from_str() {
+        assert_eq!(Encoding::try_from("gzip").unwrap(), Encoding::Gzip);
+        assert_eq!(
+            Encoding::try_from("x-foo").unwrap(),
+            Encoding::Custom("x-foo".to_string())
+        );
+        assert!(matches!(
+            Encoding::try_from("x foo"),
+            Err(EncodingError::InvalidToken(ref t)) if t == "x foo"
+        ));
+        assert!(Encoding::try_from("gzip;q=1").is_err());
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        assert_eq!(Encoding::from_bytes(b"GZIP").unwrap(), Encoding::Gzip);
+        assert_eq!(
+            Encoding::from_bytes(b"x-foo").unwrap(),
+            Encoding::Custom("x-foo".to_string())
+        );
+        assert!(matches!(
+            Encoding::from_bytes(b"x@foo"),
+            Err(EncodingError::InvalidToken(ref t)) if t == "x@foo"
+        ));
+        assert!(Encoding::from_bytes(b"x-f\xc3\xb6o").is_err());
+        assert!(Encoding::from_bytes(b"").is_err());
+
+        let max = "a".repeat(Encoding::MAX_TOKEN_LEN);
+        assert!(Encoding::from_bytes(max.as_bytes()).is_ok());
+        let too_long = "a".repeat(Encoding::MAX_TOKEN_LEN + 1);
+        assert!(matches!(
+            Encoding::from_bytes(too_long.as_bytes()),
+            Err(EncodingError::TokenTooLong(len)) if len == Encoding::MAX_TOKEN_LEN + 1
+        ));
+    }
+
+    #[test]
+    fn test_is_stream_compressible() {
+        for encoding in [Encoding::Gzip, Encoding::Br, Encoding::Zstd, Encoding::Identity] {
+            assert!(encoding.is_stream_compressible(), "{encoding}");
+        }
+        for encoding in [
+            Encoding::Bzip2,
+            Encoding::Compress,
+            Encoding::Lzma,
+            Encoding::Wildcard,
+            Encoding::Custom("x-foo".to_string()),
+        ] {
+            assert!(!encoding.is_stream_compressible(), "{encoding}");
+        }
+    }
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(Encoding::Gzip.kind(), EncodingKind::WellKnown);
+        assert_eq!(Encoding::Identity.kind(), EncodingKind::WellKnown);
+        assert_eq!(Encoding::Zlib.kind(), EncodingKind::WellKnown);
+        assert_eq!(Encoding::Wildcard.kind(), EncodingKind::Wildcard);
+        assert_eq!(
+            Encoding::Custom("x-custom".to_string()).kind(),
+            EncodingKind::Custom
+        );
+        assert_eq!(
+            Encoding::from_str("x-custom").unwrap().kind(),
+            EncodingKind::Custom
+        );
+    }
 }
@@ -1,7 +1,31 @@
 mod accept_encoding;
+#[cfg(feature = "codecs")]
+mod codec;
 mod content_encoding;
 mod encoding;
+pub mod negotiation;
 
 pub use accept_encoding::*;
+#[cfg(feature = "codecs")]
+pub use codec::*;
 pub use content_encoding::*;
 pub use encoding::*;
+pub use negotiation::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_types_are_send_sync() {
+        assert_send_sync::<AcceptEncoding>();
+        assert_send_sync::<ContentEncoding>();
+        assert_send_sync::<Encoding>();
+        assert_send_sync::<NegotiationResult>();
+        assert_send_sync::<AcceptEncodingParser>();
+        assert_send_sync::<PreservedHeaderValue>();
+        assert_send_sync::<AcceptEncodingDecodeError>();
+    }
+}
@@ -0,0 +1,495 @@
+//! Content coding negotiation following RFC 9110, section 12.5.3.
+//!
+//! All functions operate on a client's `AcceptEncoding` and the encodings a server
+//! is able to produce. The rules are:
+//!
+//! - An encoding which is not listed falls back to the quality value of the
+//!   wildcard `*`, if present. Otherwise it is not acceptable.
+//! - An encoding with `q=0` is not acceptable.
+//! - Identity is always acceptable unless rejected with `identity;q=0`, or with
+//!   `*;q=0` while identity itself is not listed.
+//!
+//! ```
+//! use http_encoding_headers::{AcceptEncoding, Encoding, decode_header_value};
+//! use http_encoding_headers::negotiation::{NegotiationResult, negotiate};
+//!
+//! let server = [Encoding::Gzip, Encoding::Compress];
+//! let accept = |value| AcceptEncoding::new(decode_header_value(value).unwrap()).unwrap();
+//!
+//! // Accept-Encoding: compress, gzip
+//! assert_eq!(
+//!     negotiate(&accept("compress, gzip"), &server),
+//!     NegotiationResult::Selected(Encoding::Gzip)
+//! );
+//!
+//! // Accept-Encoding:
+//! assert_eq!(
+//!     negotiate(&AcceptEncoding::parse("").unwrap(), &server),
+//!     NegotiationResult::Identity
+//! );
+//!
+//! // Accept-Encoding: *
+//! assert_eq!(
+//!     negotiate(&accept("*"), &server),
+//!     NegotiationResult::Selected(Encoding::Gzip)
+//! );
+//!
+//! // Accept-Encoding: compress;q=0.5, gzip;q=1.0
+//! assert_eq!(
+//!     negotiate(&accept("compress;q=0.5, gzip;q=1.0"), &[Encoding::Compress]),
+//!     NegotiationResult::Selected(Encoding::Compress)
+//! );
+//!
+//! // Accept-Encoding: gzip;q=1.0, identity; q=0.5, *;q=0
+//! let client = accept("gzip;q=1.0, identity; q=0.5, *;q=0");
+//! assert_eq!(
+//!     negotiate(&client, &server),
+//!     NegotiationResult::Selected(Encoding::Gzip)
+//! );
+//! assert_eq!(negotiate(&client, &[Encoding::Compress]), NegotiationResult::Identity);
+//! assert_eq!(
+//!     negotiate(&accept("br, *;q=0"), &[Encoding::Gzip]),
+//!     NegotiationResult::NotAcceptable
+//! );
+//! ```
+
+use crate::accept_encoding::AcceptEncoding;
+use crate::content_encoding::ContentEncoding;
+use crate::encoding::{Encoding, QualityValue};
+
+#[cfg(feature = "negotiation_cache")]
+mod cache;
+#[cfg(feature = "negotiation_cache")]
+pub use cache::NegotiationCache;
+
+/// Outcome of negotiating a content coding with `negotiate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NegotiationResult {
+    /// A content coding supported by the server was selected
+    Selected(Encoding),
+    /// No content coding should be applied
+    Identity,
+    /// Neither a supported content coding nor identity is acceptable to the client
+    NotAcceptable,
+}
+
+/// Returns the quality value the client assigns to `encoding`, falling back to the
+/// wildcard entry if it is not listed. `None` means neither is present.
+pub fn effective_quality(accept: &AcceptEncoding, encoding: &Encoding) -> Option<QualityValue> {
+    accept.explicit_quality(encoding).or_else(|| {
+        accept
+            .items()
+            .iter()
+            .filter(|(enc, _)| enc.encoding_matches(encoding))
+            .map(|(_, q)| *q)
+            .max_by(QualityValue::total_cmp)
+    })
+}
+
+/// Returns true unless the client rejected identity with `identity;q=0`, or with
+/// `*;q=0` while not listing identity itself.
+pub fn identity_acceptable(accept: &AcceptEncoding) -> bool {
+    effective_quality(accept, &Encoding::Identity).is_none_or(|q| q > 0.0)
+}
+
+/// Negotiates the content coding to use for a response.
+///
+/// Each encoding in `server` is weighted with the quality value the client
+/// assigned to it, falling back to the wildcard entry if it is not listed.
+/// An `Encoding::Wildcard` in `server` means the server supports every encoding,
+/// so every concrete encoding the client lists becomes a candidate.
+/// The acceptable encoding with the highest quality value is selected. Ties are
+/// broken by `Encoding::default_priority`, then by the order of `server`.
+///
+/// Identity is always available. It is selected if it is explicitly listed with
+/// a higher quality value than every candidate, or if no candidate is acceptable
+/// and identity was not rejected with `identity;q=0` or `*;q=0`.
+pub fn negotiate(accept: &AcceptEncoding, server: &[Encoding]) -> NegotiationResult {
+    let server: Vec<(Encoding, QualityValue)> =
+        server.iter().map(|enc| (enc.clone(), 1.0)).collect();
+    negotiate_weighted(accept, &server)
+}
+
+/// Like `negotiate`, but with a server-side weight per encoding.
+///
+/// The score of an encoding is the client's quality value multiplied by the
+/// server weight. Encodings with a weight of zero are never selected.
+pub fn negotiate_weighted(
+    accept: &AcceptEncoding,
+    server: &[(Encoding, QualityValue)],
+) -> NegotiationResult {
+    match scores(accept, server).into_iter().next() {
+        Some((Encoding::Identity, _)) => NegotiationResult::Identity,
+        Some((enc, _)) => NegotiationResult::Selected(enc),
+        None if identity_acceptable(accept) => NegotiationResult::Identity,
+        None => NegotiationResult::NotAcceptable,
+    }
+}
+
+/// Returns true if `a` and `b` negotiate the same result against `server`.
+///
+/// Useful to collapse cache variants of different Accept-Encoding values which
+/// end up with the same response.
+pub fn negotiates_same(a: &AcceptEncoding, b: &AcceptEncoding, server: &[Encoding]) -> bool {
+    negotiate(a, server) == negotiate(b, server)
+}
+
+/// Returns the distinct encodings `negotiate` selects for any of `headers`, in
+/// order of first selection.
+///
+/// This is the set of representations worth pre-generating, e.g. by compressing
+/// static assets at build time. `Encoding::Identity` is included if some client
+/// gets the unencoded body. Clients which cannot be served at all are skipped.
+pub fn representation_encodings(headers: &[AcceptEncoding], server: &[Encoding]) -> Vec<Encoding> {
+    let mut encodings: Vec<Encoding> = Vec::new();
+    for accept in headers {
+        let encoding = match negotiate(accept, server) {
+            NegotiationResult::Selected(enc) => enc,
+            NegotiationResult::Identity => Encoding::Identity,
+            NegotiationResult::NotAcceptable => continue,
+        };
+        if !encodings.contains(&encoding) {
+            encodings.push(encoding);
+        }
+    }
+    encodings
+}
+
+/// Returns the content coding to use for a response, defaulting to identity.
+///
+/// This is `negotiate` collapsed into a single `Encoding`: a selected encoding is
+/// returned as is, both `NegotiationResult::Identity` and
+/// `NegotiationResult::NotAcceptable` yield `Encoding::Identity`. When the client
+/// rejected identity as well, identity is still returned and the caller should
+/// respond with 406 Not Acceptable. Use `negotiate` to tell these cases apart.
+pub fn best_or_identity(accept: &AcceptEncoding, server: &[Encoding]) -> Encoding {
+    match negotiate(accept, server) {
+        NegotiationResult::Selected(enc) => enc,
+        NegotiationResult::Identity | NegotiationResult::NotAcceptable => Encoding::Identity,
+    }
+}
+
+/// Returns every acceptable encoding of `server` with its effective score,
+/// best first, exactly as `negotiate_weighted` ranks them.
+///
+/// The first entry is the encoding `negotiate_weighted` selects. Identity is
+/// only listed if the client explicitly assigned it a positive quality value, and
+/// always ranks last among equal scores.
+pub fn scores(
+    accept: &AcceptEncoding,
+    server: &[(Encoding, QualityValue)],
+) -> Vec<(Encoding, QualityValue)> {
+    let priority = Encoding::default_priority();
+    let rank = |enc: &Encoding| match enc {
+        Encoding::Identity => usize::MAX,
+        _ => priority
+            .iter()
+            .position(|p| p == enc)
+            .unwrap_or(priority.len()),
+    };
+
+    // A server wildcard stands for every concrete encoding the client lists but
+    // the server does not
+    let wildcard = server
+        .iter()
+        .enumerate()
+        .find(|(_, (enc, _))| matches!(enc, Encoding::Wildcard));
+    let expanded = wildcard.into_iter().flat_map(|(index, (_, weight))| {
+        accept
+            .items()
+            .iter()
+            .filter(|(enc, _)| !server.iter().any(|(server_enc, _)| server_enc == enc))
+            .map(move |(enc, _)| (index, (enc, weight)))
+    });
+
+    let mut scored: Vec<(usize, Encoding, QualityValue)> = Vec::new();
+    let candidates = server
+        .iter()
+        .enumerate()
+        .map(|(index, (enc, weight))| (index, (enc, weight)))
+        .chain(expanded);
+    for (index, (enc, weight)) in candidates {
+        if matches!(enc, Encoding::Identity | Encoding::Wildcard)
+            || scored.iter().any(|(_, scored_enc, _)| scored_enc == enc)
+        {
+            continue;
+        }
+        if let Some(q) = effective_quality(accept, enc)
+            && q * weight > 0.0
+        {
+            scored.push((index, enc.clone(), q * weight));
+        }
+    }
+
+    if let Some(q) = accept.explicit_quality(&Encoding::Identity) {
+        let weight = server
+            .iter()
+            .find(|(enc, _)| enc.is_identity())
+            .map_or(1.0, |(_, weight)| *weight);
+        if q * weight > 0.0 {
+            scored.push((server.len(), Encoding::Identity, q * weight));
+        }
+    }
+
+    scored.sort_by(|(index_a, enc_a, score_a), (index_b, enc_b, score_b)| {
+        score_b
+            .total_cmp(score_a)
+            .then_with(|| rank(enc_a).cmp(&rank(enc_b)))
+            .then_with(|| index_a.cmp(index_b))
+    });
+    scored
+        .into_iter()
+        .map(|(_, enc, score)| (enc, score))
+        .collect()
+}
+
+/// Selects the best encoding of `server` like `scores`, adding `bias_bonus` to the
+/// score of `bias` first.
+///
+/// Implements a "prefer what we have" policy, e.g. for a cache holding a gzip
+/// body which would rather serve it than re-compress to a slightly preferred br.
+/// The bonus is only applied if `bias` is acceptable to the client at all.
+pub fn preferred_allowed_biased(
+    accept: &AcceptEncoding,
+    server: &[(Encoding, QualityValue)],
+    bias: &Encoding,
+    bias_bonus: QualityValue,
+) -> Option<Encoding> {
+    let mut best: Option<(Encoding, QualityValue)> = None;
+    // Scores are already ordered by their tie-breakers, so only a strictly
+    // higher score may replace the current best
+    for (enc, score) in scores(accept, server) {
+        let score = if &enc == bias { score + bias_bonus } else { score };
+        if best.as_ref().is_none_or(|(_, best_score)| score > *best_score) {
+            best = Some((enc, score));
+        }
+    }
+    best.map(|(enc, _)| enc)
+}
+
+/// Returns true if a body already encoded with `content` can be passed through
+/// as-is, because the client accepts every applied encoding.
+///
+/// Stacked encodings are only acceptable if each of them is. A body without any
+/// encoding needs identity to be acceptable.
+pub fn passthrough_possible(content: &ContentEncoding, accept: &AcceptEncoding) -> bool {
+    if content.is_empty() {
+        return identity_acceptable(accept);
+    }
+    content.encodings().iter().all(|enc| match enc {
+        Encoding::Identity => identity_acceptable(accept),
+        _ => effective_quality(accept, enc).is_some_and(|q| q > 0.0),
+    })
+}
+
+/// Returns whether a response body of `body_len` bytes should be compressed with
+/// the negotiated `chosen` encoding.
+///
+/// Compressing tiny bodies wastes CPU and can even enlarge them, so bodies smaller
+/// than `min_size` bytes are not compressed. This is advisory and content-agnostic.
+pub fn should_compress(body_len: usize, min_size: usize, chosen: &Encoding) -> bool {
+    body_len >= min_size && !chosen.is_identity()
+}
+
+/// Computes the Accept-Encoding a proxy should forward upstream, given the
+/// encodings `proxy_supported` it is able to decode.
+///
+/// Client entries the proxy cannot decode are dropped, the remaining ones keep their
+/// quality values. A wildcard is expanded into the supported encodings the client
+/// did not list, so upstream never picks an encoding the proxy cannot handle.
+/// Identity is always included with the client's quality value for it, or q=1 if
+/// the client did not mention it.
+pub fn intersect(client: &AcceptEncoding, proxy_supported: &[Encoding]) -> AcceptEncoding {
+    let mut encodings: Vec<(Encoding, QualityValue)> = client
+        .items()
+        .iter()
+        .filter(|(enc, _)| !enc.is_identity() && proxy_supported.contains(enc))
+        .cloned()
+        .collect();
+
+    if let Some(wildcard_q) = client.explicit_quality(&Encoding::Wildcard)
+        && wildcard_q > 0.0
+    {
+        for enc in proxy_supported {
+            if !matches!(enc, Encoding::Identity | Encoding::Wildcard) && !client.contains(enc) {
+                encodings.push((enc.clone(), wildcard_q));
+            }
+        }
+    }
+
+    let identity_q = effective_quality(client, &Encoding::Identity).unwrap_or(1.0);
+    encodings.push((Encoding::Identity, identity_q));
+
+    AcceptEncoding::from_unchecked(encodings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_acceptable() {
+        let enc = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0)]).unwrap();
+        assert!(identity_acceptable(&enc));
+
+        let enc = AcceptEncoding::new(vec![(Encoding::Identity, 0.0)]).unwrap();
+        assert!(!identity_acceptable(&enc));
+
+        let enc = AcceptEncoding::new(vec![(Encoding::Wildcard, 0.0)]).unwrap();
+        assert!(!identity_acceptable(&enc));
+
+        let enc =
+            AcceptEncoding::new(vec![(Encoding::Identity, 0.5), (Encoding::Wildcard, 0.0)])
+                .unwrap();
+        assert!(identity_acceptable(&enc));
+    }
+
+    #[test]
+    fn test_best_or_identity() {
+        let accept = |value| AcceptEncoding::parse(value).unwrap();
+        let server = [Encoding::Gzip, Encoding::Br];
+        assert_eq!(best_or_identity(&accept("br;q=0.5, gzip"), &server), Encoding::Gzip);
+        assert_eq!(best_or_identity(&accept("zstd"), &server), Encoding::Identity);
+        assert_eq!(best_or_identity(&accept(""), &server), Encoding::Identity);
+
+        // Identity is returned even when forbidden, `negotiate` tells it apart
+        let forbidden = accept("zstd, *;q=0");
+        assert_eq!(best_or_identity(&forbidden, &server), Encoding::Identity);
+        assert_eq!(negotiate(&forbidden, &server), NegotiationResult::NotAcceptable);
+    }
+
+    #[test]
+    fn test_negotiates_same() {
+        let a = AcceptEncoding::parse("gzip, br").unwrap();
+        let b = AcceptEncoding::parse("br;q=0.9, gzip").unwrap();
+        assert!(negotiates_same(&a, &b, &[Encoding::Gzip]));
+        assert!(!negotiates_same(&a, &b, &[Encoding::Br, Encoding::Gzip]));
+    }
+
+    #[test]
+    fn test_representation_encodings() {
+        let headers = ["br, gzip", "gzip;q=0.8, br", "gzip", "GZIP, deflate", "identity", "*;q=0"]
+            .map(|value| AcceptEncoding::parse(value).unwrap());
+        let server = [Encoding::Br, Encoding::Gzip, Encoding::Zstd];
+        assert_eq!(
+            representation_encodings(&headers, &server),
+            [Encoding::Br, Encoding::Gzip, Encoding::Identity]
+        );
+        assert!(representation_encodings(&[], &server).is_empty());
+    }
+
+    #[test]
+    fn test_identity_only_idiom() {
+        let accept = AcceptEncoding::parse("identity;q=1, *;q=0").unwrap();
+        let server = [Encoding::Gzip, Encoding::Br, Encoding::Identity];
+        assert_eq!(negotiate(&accept, &server), NegotiationResult::Identity);
+        assert_eq!(
+            negotiate(&accept, &[Encoding::Gzip, Encoding::Br]),
+            NegotiationResult::Identity
+        );
+        assert_eq!(negotiate(&accept, &[Encoding::Wildcard]), NegotiationResult::Identity);
+        assert_eq!(accept.preferred_allowed(server.iter()), Some(&Encoding::Identity));
+        assert_eq!(accept.preferred_allowed([Encoding::Gzip].iter()), None);
+    }
+
+    #[test]
+    fn test_server_wildcard() {
+        let accept = AcceptEncoding::new(vec![
+            (Encoding::Gzip, 0.5),
+            (Encoding::Br, 0.9),
+            (Encoding::Zstd, 0.0),
+            (Encoding::Wildcard, 0.1),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            negotiate(&accept, &[Encoding::Wildcard]),
+            NegotiationResult::Selected(Encoding::Br)
+        );
+        assert_eq!(
+            accept.preferred_allowed([Encoding::Wildcard].iter()),
+            Some(&Encoding::Br)
+        );
+
+        // Explicit server entries keep their own weight
+        let server = [(Encoding::Br, 0.1), (Encoding::Wildcard, 1.0)];
+        assert_eq!(
+            negotiate_weighted(&accept, &server),
+            NegotiationResult::Selected(Encoding::Gzip)
+        );
+
+        // Rejected encodings stay rejected
+        let accept =
+            AcceptEncoding::new(vec![(Encoding::Zstd, 0.0), (Encoding::Deflate, 0.2)]).unwrap();
+        assert_eq!(
+            negotiate(&accept, &[Encoding::Wildcard]),
+            NegotiationResult::Selected(Encoding::Deflate)
+        );
+        let mut sorted = accept.clone();
+        sorted.sort_descending();
+        assert_eq!(
+            sorted.preferred_allowed([Encoding::Wildcard].iter()),
+            Some(&Encoding::Deflate)
+        );
+    }
+
+    #[test]
+    fn test_passthrough_possible() {
+        let gzip = ContentEncoding::new(Encoding::Gzip).unwrap();
+
+        let accept = AcceptEncoding::new(vec![(Encoding::Gzip, 1.0)]).unwrap();
+        assert!(passthrough_possible(&gzip, &accept));
+
+        let accept = AcceptEncoding::new(vec![(Encoding::Br, 1.0)]).unwrap();
+        assert!(!passthrough_possible(&gzip, &accept));
+
+        let accept =
+            AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Wildcard, 0.5)]).unwrap();
+        assert!(passthrough_possible(&gzip, &accept));
+
+        let accept =
+            AcceptEncoding::new(vec![(Encoding::Gzip, 0.0), (Encoding::Wildcard, 1.0)]).unwrap();
+        assert!(!passthrough_possible(&gzip, &accept));
+    }
+
+    #[test]
+    fn test_intersect_strips_unsupported() {
+        let client = AcceptEncoding::new(vec![
+            (Encoding::Br, 1.0),
+            (Encoding::Gzip, 0.8),
+            (Encoding::Identity, 0.1),
+        ])
+        .unwrap();
+
+        let forwarded = intersect(&client, &[Encoding::Gzip, Encoding::Deflate]);
+        assert_eq!(
+            forwarded.items(),
+            &[(Encoding::Gzip, 0.8), (Encoding::Identity, 0.1)]
+        );
+    }
+
+    #[test]
+    fn test_intersect_expands_wildcard_and_adds_identity() {
+        let client =
+            AcceptEncoding::new(vec![(Encoding::Br, 1.0), (Encoding::Wildcard, 0.5)]).unwrap();
+
+        let forwarded = intersect(&client, &[Encoding::Gzip]);
+        assert_eq!(
+            forwarded.items(),
+            &[(Encoding::Gzip, 0.5), (Encoding::Identity, 0.5)]
+        );
+
+        let client = AcceptEncoding::new(vec![(Encoding::Br, 1.0)]).unwrap();
+        let forwarded = intersect(&client, &[Encoding::Gzip]);
+        assert_eq!(forwarded.items(), &[(Encoding::Identity, 1.0)]);
+    }
+
+    #[test]
+    fn test_should_compress() {
+        assert!(!should_compress(1023, 1024, &Encoding::Gzip));
+        assert!(should_compress(1024, 1024, &Encoding::Gzip));
+        assert!(should_compress(1025, 1024, &Encoding::Gzip));
+        assert!(!should_compress(1024, 1024, &Encoding::Identity));
+        assert!(!should_compress(0, 0, &Encoding::Identity));
+    }
+}
@@ -0,0 +1,183 @@
+//! Memoized negotiation for a fixed set of server encodings
+
+use super::{NegotiationResult, negotiate};
+use crate::accept_encoding::{AcceptEncoding, decode_header_value_relaxed};
+use crate::encoding::Encoding;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+/// Caches `negotiate` results by raw Accept-Encoding header value, for servers
+/// which see the same few values over and over.
+///
+/// The cache holds at most `capacity` values and evicts the least recently used
+/// one. It is safe to share between threads, `negotiate` takes `&self` and locks
+/// an internal `Mutex`, so wrap it in an `Arc` rather than another lock.
+#[derive(Debug)]
+pub struct NegotiationCache {
+    server: Vec<Encoding>,
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, (NegotiationResult, u64)>,
+    // Last use tick to header value, the first entry is the least recently used
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl NegotiationCache {
+    /// Creates a cache negotiating against `server`, holding at most `capacity`
+    /// header values. A capacity of zero disables caching.
+    pub fn new(server: Vec<Encoding>, capacity: usize) -> Self {
+        Self {
+            server,
+            capacity,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Negotiates the content coding for an Accept-Encoding header value.
+    ///
+    /// The value is decoded with `decode_header_value_relaxed`, a blank value is
+    /// identity only like in `AcceptEncoding::parse`. Relaxed decoding skips
+    /// malformed entries instead of rejecting the value, so results do not always
+    /// match negotiating on `AcceptEncoding::parse`: `identity;q=0, gzip;q=x` is
+    /// `NotAcceptable` here, while `parse` fails.
+    ///
+    /// The lock is not held while decoding and negotiating a missed value, so
+    /// concurrent misses for the same value may each compute the result.
+    pub fn negotiate(&self, header: &str) -> NegotiationResult {
+        if let Some(result) = self.lock().touch(header) {
+            return result;
+        }
+
+        let accept = if header.trim().is_empty() {
+            AcceptEncoding::identity_only()
+        } else {
+            AcceptEncoding::from_unchecked(decode_header_value_relaxed(header))
+        };
+        let result = negotiate(&accept, &self.server);
+        if self.capacity == 0 {
+            return result;
+        }
+
+        let mut state = self.lock();
+        // Another thread may have inserted the value in the meantime
+        if state.touch(header).is_none() {
+            if state.entries.len() >= self.capacity
+                && let Some((_, evicted)) = state.recency.pop_first()
+            {
+                state.entries.remove(&evicted);
+            }
+            state.tick += 1;
+            let tick = state.tick;
+            state.entries.insert(header.to_string(), (result.clone(), tick));
+            state.recency.insert(tick, header.to_string());
+        }
+        result
+    }
+
+    /// Returns the number of cached header values
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns true if nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the result for `header` is cached
+    pub fn contains(&self, header: &str) -> bool {
+        self.lock().entries.contains_key(header)
+    }
+
+    /// Locks the state, ignoring poisoning as every update leaves it consistent
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CacheState {
+    /// Returns the cached result for `header`, marking it as most recently used
+    fn touch(&mut self, header: &str) -> Option<NegotiationResult> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (result, last_used) = self.entries.get_mut(header)?;
+        let result = result.clone();
+        let previous = std::mem::replace(last_used, tick);
+        let key = self.recency.remove(&previous).unwrap();
+        self.recency.insert(tick, key);
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_hit_returns_same_result() {
+        let cache = NegotiationCache::new(vec![Encoding::Gzip, Encoding::Br], 8);
+        let first = cache.negotiate("gzip;q=0.5, br");
+        assert_eq!(first, NegotiationResult::Selected(Encoding::Br));
+        assert!(cache.contains("gzip;q=0.5, br"));
+
+        let second = cache.negotiate("gzip;q=0.5, br");
+        assert_eq!(first, second);
+        assert_eq!(cache.len(), 1);
+
+        assert_eq!(cache.negotiate(""), NegotiationResult::Identity);
+        assert_eq!(cache.negotiate("zstd, *;q=0"), NegotiationResult::NotAcceptable);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let cache = NegotiationCache::new(vec![Encoding::Gzip], 2);
+        cache.negotiate("gzip");
+        cache.negotiate("br");
+        // Touch "gzip", so "br" is the least recently used
+        cache.negotiate("gzip");
+        cache.negotiate("zstd");
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains("gzip"));
+        assert!(!cache.contains("br"));
+        assert!(cache.contains("zstd"));
+    }
+
+    #[test]
+    fn test_cache_zero_capacity() {
+        let cache = NegotiationCache::new(vec![Encoding::Gzip], 0);
+        assert_eq!(cache.negotiate("gzip"), NegotiationResult::Selected(Encoding::Gzip));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_cache_decodes_relaxed() {
+        let cache = NegotiationCache::new(vec![Encoding::Gzip], 8);
+        let header = "identity;q=0, gzip;q=x";
+        assert!(AcceptEncoding::parse(header).is_err());
+        assert_eq!(cache.negotiate(header), NegotiationResult::NotAcceptable);
+    }
+
+    #[test]
+    fn test_cache_shared_between_threads() {
+        let cache = NegotiationCache::new(vec![Encoding::Gzip, Encoding::Br], 2);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for header in ["gzip", "br", "zstd, gzip;q=0.5"] {
+                        let accept = AcceptEncoding::parse(header).unwrap();
+                        let expected = negotiate(&accept, &cache.server);
+                        assert_eq!(cache.negotiate(header), expected);
+                    }
+                });
+            }
+        });
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,81 @@
+//! Verifies allocation guarantees with a counting global allocator.
+//!
+//! Allocations are counted per thread, so tests running in parallel do not
+//! disturb each other.
+
+use http_encoding_headers::{Encoding, decode_header_value, decode_header_value_into};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::hint::black_box;
+
+/// Counts the allocations, including reallocations, of the current thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const BROWSER_VALUES: [&str; 4] = [
+    "gzip, deflate",
+    "gzip, deflate, br",
+    "gzip, deflate, br, zstd",
+    "gzip, deflate, br, zstd, identity;q=0.5, *;q=0",
+];
+
+/// Returns the number of allocations `f` makes on the current thread
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn as_str_does_not_allocate() {
+    let custom = Encoding::Custom("x-foo".to_string());
+    for encoding in [Encoding::Gzip, Encoding::Wildcard, custom] {
+        let count = allocations(|| {
+            black_box(black_box(&encoding).as_str());
+        });
+        assert_eq!(count, 0, "{encoding:?}");
+    }
+}
+
+#[test]
+fn decode_header_value_allocates_once() {
+    for value in BROWSER_VALUES {
+        let count = allocations(|| {
+            black_box(decode_header_value(black_box(value)).unwrap());
+        });
+        assert_eq!(count, 1, "{value:?}");
+    }
+}
+
+#[test]
+fn decode_header_value_into_reused_buffer_does_not_allocate() {
+    let mut buffer = Vec::with_capacity(8);
+    for value in BROWSER_VALUES {
+        let count = allocations(|| {
+            decode_header_value_into(black_box(value), &mut buffer).unwrap();
+        });
+        assert_eq!(count, 0, "{value:?}");
+    }
+}